@@ -0,0 +1,208 @@
+use crate::data_types::NoteValue;
+
+// --- CHANNEL LAYOUT ---
+
+/// Interleaves the planar channels into a single buffer.
+/// Channels shorter than the longest channel are padded with zeros.
+pub fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    let frames = planar_frames(channels);
+    let mut interleaved = vec![0.0; frames * channels.len()];
+    for (channel, samples) in channels.iter().enumerate() {
+        for (frame, sample) in samples.iter().take(frames).enumerate() {
+            interleaved[frame * channels.len() + channel] = *sample;
+        }
+    }
+    interleaved
+}
+
+/// Splits the interleaved buffer into planar channels.
+/// A trailing partial frame is dropped.
+pub fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let mut planar = vec![Vec::with_capacity(frames); channels];
+    for frame in samples.chunks_exact(channels) {
+        for (channel, sample) in planar.iter_mut().zip(frame) {
+            channel.push(*sample);
+        }
+    }
+    planar
+}
+
+/// Returns the number of frames of the planar channels, which is the length of the longest channel.
+pub fn planar_frames(channels: &[Vec<f32>]) -> usize {
+    channels.iter().map(|c| c.len()).max().unwrap_or(0)
+}
+
+/// Returns whether every planar channel has the same length.
+/// Ragged channels are usually a bug in the code that produced them, so check this before processing.
+pub fn is_rectangular(channels: &[Vec<f32>]) -> bool {
+    channels
+        .windows(2)
+        .all(|pair| pair[0].len() == pair[1].len())
+}
+
+/// Pads the planar channels shorter than the longest channel with zeros, so every channel has the same length.
+pub fn pad_to_rectangular(channels: &mut [Vec<f32>]) {
+    let frames = planar_frames(channels);
+    for channel in channels.iter_mut() {
+        channel.resize(frames, 0.0);
+    }
+}
+
+// --- FRAMING ---
+
+/// Splits the interleaved buffer into overlapping frames of `frame_size` frames, starting every `hop_size` frames.
+/// The frames cover the whole buffer, and the last partial frame is padded with zeros.
+pub fn chunk_buffer_overlapped(
+    samples: &[f32],
+    channels: usize,
+    frame_size: usize,
+    hop_size: usize,
+) -> Vec<Vec<f32>> {
+    let channels = channels.max(1);
+    let hop_size = hop_size.max(1);
+    let total_frames = samples.len() / channels;
+    if total_frames == 0 || frame_size == 0 {
+        return Vec::new();
+    }
+
+    // Calculate the number of frames needed to cover the buffer
+    let frame_count = total_frames.saturating_sub(frame_size).div_ceil(hop_size) + 1;
+    (0..frame_count)
+        .map(|index| {
+            let start = index * hop_size * channels;
+            let end = (start + frame_size * channels).min(total_frames * channels);
+            let mut frame = vec![0.0; frame_size * channels];
+            frame[..end - start].copy_from_slice(&samples[start..end]);
+            frame
+        })
+        .collect()
+}
+
+/// Reconstructs the interleaved buffer from the overlapping frames by adding each frame at every `hop_size` frames.
+/// The inverse of `chunk_buffer_overlapped` when the frames are windowed so that the overlapping windows sum to one.
+pub fn overlap_add(frames: &[Vec<f32>], channels: usize, hop_size: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    let hop = hop_size.max(1) * channels;
+    let len = frames
+        .iter()
+        .enumerate()
+        .map(|(index, frame)| index * hop + frame.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = vec![0.0; len];
+    for (index, frame) in frames.iter().enumerate() {
+        for (dst, src) in output[index * hop..].iter_mut().zip(frame) {
+            *dst += *src;
+        }
+    }
+    output
+}
+
+// --- TEMPO SYNC ---
+
+/// Converts the note value to the number of samples at the given tempo and sample rate.
+/// Useful for tempo-synced time parameters such as delay times.
+pub fn note_value_to_samples(note_value: NoteValue, bpm: f64, sample_rate: usize) -> usize {
+    (note_value.to_beats().to_seconds(bpm) * sample_rate as f64).round() as usize
+}
+
+// --- BUFFER ARITHMETIC ---
+
+/// Adds the source samples multiplied by the gain to the destination.
+/// Only the samples in the shorter of the two buffers are mixed.
+pub fn mix_into(dst: &mut [f32], src: &[f32], gain: f32) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d += *s * gain;
+    }
+}
+
+/// Multiplies the samples by the gain in place.
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    samples.iter_mut().for_each(|sample| *sample *= gain);
+}
+
+/// Multiplies the interleaved samples by a gain moving linearly from `from` to `to` across the frames,
+/// reaching `to` at the last frame. Ramping a changing gain prevents the click of a sudden step.
+pub fn apply_gain_ramp(samples: &mut [f32], channels: usize, from: f32, to: f32) {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let step = (to - from) / frames.max(1) as f32;
+    for (frame, samples) in samples.chunks_exact_mut(channels).enumerate() {
+        let gain = from + step * (frame + 1) as f32;
+        samples.iter_mut().for_each(|sample| *sample *= gain);
+    }
+}
+
+/// Replaces the NaN and infinite samples with 0.0 in place, and returns how many were replaced.
+pub fn sanitize_samples(samples: &mut [f32]) -> usize {
+    let mut replaced = 0;
+    for sample in samples.iter_mut() {
+        if !sample.is_finite() {
+            *sample = 0.0;
+            replaced += 1;
+        }
+    }
+    replaced
+}
+
+/// Clamps the samples between -1.0 and 1.0 in place.
+pub fn clamp_samples(samples: &mut [f32]) {
+    samples
+        .iter_mut()
+        .for_each(|sample| *sample = sample.clamp(-1.0, 1.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleave_round_trips_an_odd_channel_count() {
+        let planar = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]];
+        let interleaved = interleave(&planar);
+        assert_eq!(interleaved, [1.0, 3.0, 5.0, 2.0, 4.0, 6.0]);
+        assert_eq!(deinterleave(&interleaved, 3), planar);
+    }
+
+    #[test]
+    fn interleave_pads_ragged_channels() {
+        let planar = vec![vec![1.0, 2.0, 3.0], vec![4.0]];
+        let interleaved = interleave(&planar);
+        assert_eq!(interleaved, [1.0, 4.0, 2.0, 0.0, 3.0, 0.0]);
+        assert_eq!(
+            deinterleave(&interleaved, 2),
+            [vec![1.0, 2.0, 3.0], vec![4.0, 0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn deinterleave_drops_a_partial_frame() {
+        let planar = deinterleave(&[1.0, 2.0, 3.0, 4.0, 5.0], 2);
+        assert_eq!(planar, [vec![1.0, 3.0], vec![2.0, 4.0]]);
+    }
+
+    #[test]
+    fn mix_into_mixes_the_shorter_length() {
+        let mut dst = [1.0, 1.0, 1.0];
+        mix_into(&mut dst, &[1.0, 2.0], 0.5);
+        assert_eq!(dst, [1.5, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn gain_ramp_reaches_the_target_at_the_last_frame() {
+        let mut samples = [1.0; 8];
+        apply_gain_ramp(&mut samples, 2, 1.0, 0.0);
+        assert_eq!(samples, [0.75, 0.75, 0.5, 0.5, 0.25, 0.25, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn interleave_handles_empty_input() {
+        assert!(interleave(&[]).is_empty());
+        assert!(interleave(&[Vec::new(), Vec::new()]).is_empty());
+        assert_eq!(deinterleave(&[], 2), [Vec::<f32>::new(), Vec::new()]);
+        assert_eq!(deinterleave(&[], 0), [Vec::<f32>::new()]);
+    }
+}
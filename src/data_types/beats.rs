@@ -5,6 +5,18 @@ use std::ops::{Add, Div, Mul, Sub};
 #[derive(Default, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Beats(pub f64);
 
+impl Beats {
+    /// Converts the beats to seconds at the given tempo.
+    pub fn to_seconds(self, bpm: f64) -> f64 {
+        self.0 / bpm * 60.0
+    }
+
+    /// Converts the seconds to beats at the given tempo.
+    pub fn from_seconds(seconds: f64, bpm: f64) -> Self {
+        Self(seconds * bpm / 60.0)
+    }
+}
+
 impl Add for Beats {
     type Output = Self;
 
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// The sample format which the audio is delivered in.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BitDepth {
+    Int16,
+    Int24,
+    #[default]
+    Float32,
+}
+
+impl BitDepth {
+    /// Returns the number of bits of the integer formats, or None for the float format.
+    pub fn integer_bits(&self) -> Option<u32> {
+        match self {
+            BitDepth::Int16 => Some(16),
+            BitDepth::Int24 => Some(24),
+            BitDepth::Float32 => None,
+        }
+    }
+}
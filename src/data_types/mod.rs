@@ -1,11 +1,15 @@
 mod audio_context;
 mod beats;
+mod bit_depth;
 mod midi_event;
+mod note_value;
 mod type_info;
 mod voice;
 
 pub use audio_context::AudioContext;
 pub use beats::Beats;
+pub use bit_depth::BitDepth;
 pub use midi_event::MidiEvent;
+pub use note_value::{NoteModifier, NoteValue};
 pub use type_info::TypeInfo;
 pub use voice::Voice;
@@ -0,0 +1,37 @@
+use crate::data_types::Beats;
+use serde::{Deserialize, Serialize};
+
+/// The modifier which changes the length of a note value.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoteModifier {
+    #[default]
+    Straight,
+    /// 1.5 times the length.
+    Dotted,
+    /// 2/3 of the length.
+    Triplet,
+}
+
+/// A musical note length such as a quarter note or a dotted eighth note.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteValue {
+    /// The division of a whole note, such as 4 for a quarter note and 8 for an eighth note.
+    pub division: u32,
+    pub modifier: NoteModifier,
+}
+
+impl NoteValue {
+    pub fn new(division: u32, modifier: NoteModifier) -> Self {
+        Self { division, modifier }
+    }
+
+    /// Returns the length in beats, where a beat is a quarter note.
+    pub fn to_beats(&self) -> Beats {
+        let straight = 4.0 / self.division.max(1) as f64;
+        Beats(match self.modifier {
+            NoteModifier::Straight => straight,
+            NoteModifier::Dotted => straight * 1.5,
+            NoteModifier::Triplet => straight * 2.0 / 3.0,
+        })
+    }
+}
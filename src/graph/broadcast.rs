@@ -0,0 +1,87 @@
+use crate::{
+    data_types::TypeInfo,
+    graph::{Graph, error::GraphError, node_id::NodeID},
+};
+use std::collections::HashMap;
+
+impl Graph {
+    // --- CHANNEL BROADCASTING ---
+
+    /// Returns whether the output type is a mono audio buffer which can be broadcast to the input type,
+    /// which is an audio buffer with the channels of the audio context.
+    pub(super) fn is_broadcast(&self, output_type: &TypeInfo, input_type: &TypeInfo) -> bool {
+        let channels = self.audio_ctx.channels;
+        let buffer_size = self.audio_ctx.buffer_size;
+        channels > 1
+            && output_type.align == 4
+            && input_type.align == 4
+            && output_type.size == 4 * buffer_size
+            && input_type.size == 4 * channels * buffer_size
+    }
+
+    /// Allocates a buffer for every input connected to a mono output, and points the input to it.
+    /// Must be called after the inputs are connected in `prepare`.
+    pub(super) fn prepare_broadcasts(&mut self) -> Result<(), GraphError> {
+        self.broadcast_inputs.clear();
+
+        for edge in &self.edges {
+            let (Some(output_type), Some(input_type)) = (
+                self.nodes
+                    .get(&edge.0)
+                    .and_then(|node| node.get_output_type(edge.1)),
+                self.nodes
+                    .get(&edge.2)
+                    .and_then(|node| node.get_input_type(edge.3)),
+            ) else {
+                continue;
+            };
+            // The mixed inputs spread the mono signals themselves
+            if !self.is_broadcast(output_type, input_type)
+                || self.mixed_inputs.contains_key(&(edge.2, edge.3))
+            {
+                continue;
+            }
+
+            let Some(input_ptr) = self
+                .node_inputs
+                .get_mut(&edge.2)
+                .and_then(|ptrs| ptrs.get_mut(edge.3))
+            else {
+                return Err(GraphError::NodeNotPrepared(edge.2));
+            };
+            let buffer = vec![0u8; input_type.size * self.audio_ctx.buffer_size];
+            let source = *input_ptr;
+            *input_ptr = buffer.as_ptr();
+            self.broadcast_inputs
+                .insert((edge.2, edge.3), (buffer, source));
+        }
+
+        Ok(())
+    }
+
+    /// Copies the mono signal to every channel of the broadcast inputs of the node.
+    pub(super) fn apply_broadcasts(
+        node_id: &NodeID,
+        channels: usize,
+        buffer_size: usize,
+        broadcast_inputs: &mut HashMap<(NodeID, usize), (Vec<u8>, *const u8)>,
+    ) {
+        for ((target, _), (buffer, source)) in broadcast_inputs.iter_mut() {
+            if target != node_id {
+                continue;
+            }
+            let (source, samples) = unsafe {
+                (
+                    std::slice::from_raw_parts(*source as *const f32, buffer_size),
+                    std::slice::from_raw_parts_mut(
+                        buffer.as_mut_ptr() as *mut f32,
+                        channels * buffer_size,
+                    ),
+                )
+            };
+            for (frame, sample) in samples.chunks_exact_mut(channels).zip(source) {
+                frame.fill(*sample);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    data_types::TypeInfo,
+    graph::{Graph, error::GraphError, node_id::NodeID},
+};
+use std::collections::HashMap;
+
+/// The signals summed into a mixed input, as the pointer to the output, the gain and whether the output is mono.
+pub(super) type MixSources = Vec<(*const u8, f32, bool)>;
+
+impl Graph {
+    // --- EDGE GAIN ---
+
+    /// Connects the node's output to another node's input, scaling the signal by the gain.
+    /// The signals of the edges connected to the same input are summed as f32 samples, so the input works as a weighted mixer.
+    /// Returns an error if the type of the output and input are not the same, if the node is not found,
+    /// or if the input doesn't take audio. The edge is not added when an error is returned.
+    pub fn add_edge_with_gain(
+        &mut self,
+        edge: (NodeID, usize, NodeID, usize),
+        gain: f32,
+    ) -> Result<(), GraphError> {
+        self.add_edge(edge)?;
+        if let Err(err) = self.set_edge_gain(edge, gain) {
+            self.remove_edge(edge)?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Sets the gain of the edge. The graph must be prepared again after changing the gain.
+    /// Returns an error if the edge is not found, or if the input doesn't take audio, which can't be scaled.
+    pub fn set_edge_gain(
+        &mut self,
+        edge: (NodeID, usize, NodeID, usize),
+        gain: f32,
+    ) -> Result<(), GraphError> {
+        if !self.edges.contains(&edge) {
+            return Err(GraphError::EdgeNotFound(edge));
+        }
+        let input_type = self
+            .nodes
+            .get(&edge.2)
+            .and_then(|node| node.get_input_type(edge.3))
+            .ok_or(GraphError::InputTypeUnavailable(edge.2, edge.3))?;
+        if gain != 1.0 && !self.is_audio_type(input_type) {
+            return Err(GraphError::InvalidMix(edge.2, edge.3));
+        }
+        if gain == 1.0 {
+            self.edge_gains.remove(&edge);
+        } else {
+            self.edge_gains.insert(edge, gain);
+        }
+        self.mark_unprepared();
+        Ok(())
+    }
+
+    /// Returns the gain of the edge, which is 1.0 unless set with `set_edge_gain`.
+    pub fn get_edge_gain(&self, edge: &(NodeID, usize, NodeID, usize)) -> f32 {
+        self.edge_gains.get(edge).copied().unwrap_or(1.0)
+    }
+
+    /// Returns whether the type holds the f32 samples of a buffer, either in every channel or in a single channel.
+    /// Only these types can be summed and scaled.
+    pub(super) fn is_audio_type(&self, data_type: &TypeInfo) -> bool {
+        let samples = 4 * self.audio_ctx.buffer_size;
+        data_type.align == 4
+            && (data_type.size == samples * self.audio_ctx.channels || data_type.size == samples)
+    }
+
+    /// Allocates a buffer for every input connected to several edges or to an edge with a gain, and points the input to it.
+    /// Must be called after the inputs are connected in `prepare`, and before the broadcasts and the modulations.
+    /// Returns an error if such an input doesn't take audio, as other types can't be summed.
+    pub(super) fn prepare_mixes(&mut self) -> Result<(), GraphError> {
+        self.mixed_inputs.clear();
+
+        // Group the edges by the input they are connected to
+        let mut inputs: HashMap<(NodeID, usize), Vec<_>> = HashMap::new();
+        for edge in &self.edges {
+            inputs.entry((edge.2, edge.3)).or_default().push(edge);
+        }
+
+        for ((target, input), edges) in inputs {
+            let is_mixed = edges.len() > 1 || edges.iter().any(|e| self.edge_gains.contains_key(e));
+            if !is_mixed {
+                continue;
+            }
+            let input_type = self.nodes[&target]
+                .get_input_type(input)
+                .ok_or(GraphError::InputTypeUnavailable(target, input))?;
+            if !self.is_audio_type(input_type) {
+                return Err(GraphError::InvalidMix(target, input));
+            }
+
+            let mut sources = Vec::with_capacity(edges.len());
+            for edge in edges {
+                let output_type = self.nodes[&edge.0]
+                    .get_output_type(edge.1)
+                    .ok_or(GraphError::OutputTypeUnavailable(edge.0, edge.1))?;
+                let ptr = self
+                    .output_buffers
+                    .get(&(edge.0, edge.1))
+                    .map(|b| b.as_ptr())
+                    .ok_or(GraphError::OutputBufferNotFound(edge.0, edge.1))?;
+                let is_mono = self.is_broadcast(output_type, input_type);
+                sources.push((ptr, self.get_edge_gain(edge), is_mono));
+            }
+
+            let Some(input_ptr) = self
+                .node_inputs
+                .get_mut(&target)
+                .and_then(|ptrs| ptrs.get_mut(input))
+            else {
+                return Err(GraphError::NodeNotPrepared(target));
+            };
+            // The size of the audio type already covers the whole buffer
+            let buffer = vec![0u8; input_type.size];
+            *input_ptr = buffer.as_ptr();
+            self.mixed_inputs.insert((target, input), (buffer, sources));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the sum of the signals scaled by the edge gains to the mixed inputs of the node.
+    /// The mono signals are copied to every channel.
+    pub(super) fn apply_mixes(
+        node_id: &NodeID,
+        channels: usize,
+        mixed_inputs: &mut HashMap<(NodeID, usize), (Vec<u8>, MixSources)>,
+    ) {
+        for ((target, _), (buffer, sources)) in mixed_inputs.iter_mut() {
+            if target != node_id {
+                continue;
+            }
+            let len = buffer.len() / 4;
+            let samples =
+                unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut f32, len) };
+            samples.fill(0.0);
+
+            for (source, gain, is_mono) in sources.iter() {
+                if *is_mono {
+                    let source = unsafe {
+                        std::slice::from_raw_parts(*source as *const f32, len / channels)
+                    };
+                    for (frame, value) in samples.chunks_exact_mut(channels).zip(source) {
+                        frame.iter_mut().for_each(|sample| *sample += value * gain);
+                    }
+                } else {
+                    // The output buffer has the same type as the input
+                    let source = unsafe { std::slice::from_raw_parts(*source as *const f32, len) };
+                    for (sample, value) in samples.iter_mut().zip(source) {
+                        *sample += value * gain;
+                    }
+                }
+            }
+        }
+    }
+}
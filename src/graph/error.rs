@@ -1,15 +1,85 @@
 use crate::graph::node_id::NodeID;
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    time::Duration,
+};
 
 #[derive(Debug)]
 pub enum GraphError {
-    NodeError(Box<dyn NodeError>),
+    NodeError(NodeID, Box<dyn NodeError>),
+    NodeNotFound(NodeID),
+    NodeNotPrepared(NodeID),
     OutputBufferNotFound(NodeID, usize),
     NodeCycle(NodeID),
     OutputTypeUnavailable(NodeID, usize),
     InputTypeUnavailable(NodeID, usize),
     NodeTypeMismatch((NodeID, usize, NodeID, usize)),
     EdgeNotFound((NodeID, usize, NodeID, usize)),
+    InvalidFeedback(NodeID, NodeID),
+    InvalidModulation(NodeID, usize),
+    /// The input has several edges or an edge gain, but doesn't take audio which can be summed.
+    InvalidMix(NodeID, usize),
+    GraphNotPrepared,
+    InvalidTap(NodeID, usize),
+    /// The chunk ran past the time budget while the node was processing.
+    NodeTimeout(NodeID, Duration),
 }
 
+impl Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::NodeError(id, err) => write!(f, "Node {} failed: {}", id.0, err),
+            GraphError::NodeNotFound(id) => write!(f, "Node {} not found", id.0),
+            GraphError::NodeNotPrepared(id) => {
+                write!(f, "Buffers of node {} have not been prepared", id.0)
+            }
+            GraphError::OutputBufferNotFound(id, index) => {
+                write!(f, "Output buffer {} of node {} not found", index, id.0)
+            }
+            GraphError::NodeCycle(id) => write!(f, "Cycle found at node {}", id.0),
+            GraphError::OutputTypeUnavailable(id, index) => {
+                write!(f, "Type of output {} of node {} unavailable", index, id.0)
+            }
+            GraphError::InputTypeUnavailable(id, index) => {
+                write!(f, "Type of input {} of node {} unavailable", index, id.0)
+            }
+            GraphError::NodeTypeMismatch(edge) => write!(
+                f,
+                "Type of output {} of node {} does not match input {} of node {}",
+                edge.1, edge.0.0, edge.3, edge.2.0
+            ),
+            GraphError::EdgeNotFound(edge) => write!(
+                f,
+                "Edge from output {} of node {} to input {} of node {} not found",
+                edge.1, edge.0.0, edge.3, edge.2.0
+            ),
+            GraphError::InvalidFeedback(send, ret) => {
+                write!(f, "Node {} cannot feed back to node {}", send.0, ret.0)
+            }
+            GraphError::InvalidModulation(id, index) => {
+                write!(f, "Input {} of node {} cannot be modulated", index, id.0)
+            }
+            GraphError::InvalidMix(id, index) => write!(
+                f,
+                "Input {} of node {} cannot mix several edges or scale an edge",
+                index, id.0
+            ),
+            GraphError::GraphNotPrepared => write!(
+                f,
+                "Graph has not been prepared since its nodes, edges or audio context changed"
+            ),
+            GraphError::InvalidTap(id, index) => {
+                write!(f, "Output {} of node {} cannot be tapped", index, id.0)
+            }
+            GraphError::NodeTimeout(id, elapsed) => write!(
+                f,
+                "Node {} exceeded the time budget of the chunk after {:?}",
+                id.0, elapsed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
 pub trait NodeError: Send + Debug + Display {}
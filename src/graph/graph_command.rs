@@ -0,0 +1,217 @@
+use crate::{
+    graph::{Graph, Modulation, error::GraphError, node_id::NodeID},
+    node::Node,
+};
+
+/// An edit of the graph which can be undone when applied with `Graph::apply`.
+#[derive(Clone)]
+pub enum GraphCommand {
+    /// Adds the node to the graph.
+    AddNode(Box<dyn Node>),
+    /// Removes the node and the edges connected to it.
+    RemoveNode(NodeID),
+    /// Connects the output of a node to the input of another node.
+    Connect((NodeID, usize, NodeID, usize)),
+    /// Removes the edge between the nodes.
+    Disconnect((NodeID, usize, NodeID, usize)),
+    /// Replaces the input node, keeping its ID and edges.
+    SetInputNode(Box<dyn Node>),
+    /// Replaces the output node, keeping its ID and edges.
+    SetOutputNode(Box<dyn Node>),
+}
+
+/// The inverse of an applied command, which restores the graph before the command.
+#[derive(Clone)]
+pub(super) enum UndoEntry {
+    RemoveNode(NodeID),
+    RestoreNode {
+        id: NodeID,
+        node: Box<dyn Node>,
+        edges: Vec<(NodeID, usize, NodeID, usize)>,
+        edge_gains: Vec<((NodeID, usize, NodeID, usize), f32)>,
+        feedbacks: Vec<(NodeID, NodeID)>,
+        modulations: Vec<Modulation>,
+        taps: Vec<(NodeID, usize)>,
+        name: Option<String>,
+        soloed: bool,
+    },
+    /// Connects the edge again with the gain it had when it was disconnected.
+    Connect((NodeID, usize, NodeID, usize), f32),
+    Disconnect((NodeID, usize, NodeID, usize)),
+    ReplaceNode(NodeID, Box<dyn Node>),
+}
+
+impl Graph {
+    // --- UNDOABLE EDITING ---
+
+    /// Applies the command to the graph and records its inverse, so the edit can be undone with `undo`.
+    /// Returns the ID of the added node for `GraphCommand::AddNode`, and None for the other commands.
+    /// Adding, removing, replacing or connecting the nodes with the other methods of the graph clears the recorded commands,
+    /// as their inverses may no longer apply. The graph must be prepared again after applying a command.
+    pub fn apply(&mut self, command: GraphCommand) -> Result<Option<NodeID>, GraphError> {
+        // The command edits the graph with the methods which clear the history, so keep it aside
+        let mut undo_stack = std::mem::take(&mut self.undo_stack);
+        let result = self.apply_command(command).map(|(entry, added)| {
+            undo_stack.push(entry);
+            added
+        });
+        self.undo_stack = undo_stack;
+        result
+    }
+
+    /// Applies the command to the graph, and returns its inverse and the ID of the added node.
+    fn apply_command(
+        &mut self,
+        command: GraphCommand,
+    ) -> Result<(UndoEntry, Option<NodeID>), GraphError> {
+        let result = match command {
+            GraphCommand::AddNode(node) => {
+                let id = self.add_node(node);
+                (UndoEntry::RemoveNode(id), Some(id))
+            }
+            GraphCommand::RemoveNode(id) => {
+                // Keep everything connected to the node to restore it later
+                let edges = self
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.0 == id || edge.2 == id)
+                    .copied()
+                    .collect();
+                let edge_gains = self
+                    .edge_gains
+                    .iter()
+                    .filter(|(edge, _)| edge.0 == id || edge.2 == id)
+                    .map(|(edge, gain)| (*edge, *gain))
+                    .collect();
+                let feedbacks = self
+                    .feedbacks
+                    .iter()
+                    .filter(|feedback| feedback.0 == id || feedback.1 == id)
+                    .copied()
+                    .collect();
+                let modulations = self
+                    .modulations
+                    .iter()
+                    .filter(|modulation| modulation.source == id || modulation.target == id)
+                    .copied()
+                    .collect();
+                let taps = self
+                    .taps
+                    .iter()
+                    .filter(|tap| tap.0 == id)
+                    .copied()
+                    .collect();
+                let name = self.node_names.get(&id).cloned();
+                let soloed = self.soloed_node == Some(id);
+                let node = self
+                    .nodes
+                    .get(&id)
+                    .cloned()
+                    .ok_or(GraphError::NodeNotFound(id))?;
+                self.remove_node(&id);
+                (
+                    UndoEntry::RestoreNode {
+                        id,
+                        node,
+                        edges,
+                        edge_gains,
+                        feedbacks,
+                        modulations,
+                        taps,
+                        name,
+                        soloed,
+                    },
+                    None,
+                )
+            }
+            GraphCommand::Connect(edge) => {
+                self.add_edge(edge)?;
+                (UndoEntry::Disconnect(edge), None)
+            }
+            GraphCommand::Disconnect(edge) => {
+                let gain = self.get_edge_gain(&edge);
+                self.remove_edge(edge)?;
+                (UndoEntry::Connect(edge, gain), None)
+            }
+            GraphCommand::SetInputNode(node) => {
+                let old_node = self.set_input_node(node)?;
+                (UndoEntry::ReplaceNode(self.input_id, old_node), None)
+            }
+            GraphCommand::SetOutputNode(node) => {
+                let old_node = self.set_output_node(node)?;
+                (UndoEntry::ReplaceNode(self.output_id, old_node), None)
+            }
+        };
+        Ok(result)
+    }
+
+    /// Reverts the last command applied with `apply`. Returns false if there is nothing to undo.
+    /// The graph must be prepared again after undoing.
+    pub fn undo(&mut self) -> bool {
+        let Some(entry) = self.undo_stack.pop() else {
+            return false;
+        };
+
+        // Reverting edits the graph with the methods which clear the history, so keep it aside
+        let undo_stack = std::mem::take(&mut self.undo_stack);
+        self.revert(entry);
+        self.undo_stack = undo_stack;
+        true
+    }
+
+    /// Restores the graph before the command whose inverse is the entry.
+    fn revert(&mut self, entry: UndoEntry) {
+        match entry {
+            UndoEntry::RemoveNode(id) => self.remove_node(&id),
+            UndoEntry::RestoreNode {
+                id,
+                node,
+                edges,
+                edge_gains,
+                feedbacks,
+                modulations,
+                taps,
+                name,
+                soloed,
+            } => {
+                // The node is restored as it was, so the edges are valid without checking
+                self.nodes.insert(id, node);
+                self.edges.extend(edges);
+                self.edge_gains.extend(edge_gains);
+                self.feedbacks.extend(feedbacks);
+                self.modulations.extend(modulations);
+                self.taps.extend(taps);
+                if let Some(name) = name {
+                    self.node_names.insert(id, name);
+                }
+                if soloed {
+                    self.soloed_node = Some(id);
+                }
+                self.mark_unprepared();
+            }
+            UndoEntry::Connect(edge, gain) => {
+                self.add_edge_unchecked(edge);
+                if gain != 1.0 {
+                    self.edge_gains.insert(edge, gain);
+                }
+            }
+            UndoEntry::Disconnect(edge) => {
+                let _ = self.remove_edge(edge);
+            }
+            UndoEntry::ReplaceNode(id, node) => {
+                self.nodes.insert(id, node);
+                self.mark_unprepared();
+            }
+        }
+    }
+
+    /// Returns whether there is a command to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Drops the recorded commands, such as after loading a graph.
+    pub fn clear_undo_history(&mut self) {
+        self.undo_stack.clear();
+    }
+}
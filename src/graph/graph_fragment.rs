@@ -0,0 +1,126 @@
+use crate::{
+    graph::{Graph, Modulation, node_id::NodeID},
+    node::Node,
+};
+use std::collections::{HashMap, HashSet};
+
+/// A copy of the selected nodes of a graph and the connections among them, which can be pasted with `Graph::paste`.
+#[derive(Clone, Default)]
+pub struct GraphFragment {
+    /// The copied nodes with their IDs in the original graph, sorted by the ID.
+    nodes: Vec<(NodeID, Box<dyn Node>)>,
+    /// The edges between the copied nodes.
+    edges: Vec<(NodeID, usize, NodeID, usize)>,
+    /// The gains of the copied edges which don't pass the signal at unity gain.
+    edge_gains: HashMap<(NodeID, usize, NodeID, usize), f32>,
+    /// The feedback pairs between the copied nodes.
+    feedbacks: Vec<(NodeID, NodeID)>,
+    /// The modulations between the copied nodes.
+    modulations: Vec<Modulation>,
+}
+
+impl GraphFragment {
+    /// Returns the IDs of the copied nodes in the original graph.
+    pub fn get_node_ids(&self) -> Vec<NodeID> {
+        self.nodes.iter().map(|(id, _)| *id).collect()
+    }
+
+    /// Returns the edges between the copied nodes, with the IDs in the original graph.
+    pub fn get_edges(&self) -> &[(NodeID, usize, NodeID, usize)] {
+        &self.edges
+    }
+
+    /// Returns whether the fragment has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl Graph {
+    // --- COPY AND PASTE ---
+
+    /// Copies the nodes and the edges, feedbacks and modulations among them into a fragment.
+    /// The input and output nodes of the graph and the IDs not in the graph are skipped.
+    /// The connections to the nodes which are not selected are not copied.
+    pub fn extract(&self, node_ids: &[NodeID]) -> GraphFragment {
+        let selected: HashSet<NodeID> = node_ids
+            .iter()
+            .copied()
+            .filter(|id| *id != self.input_id && *id != self.output_id)
+            .filter(|id| self.nodes.contains_key(id))
+            .collect();
+
+        let mut nodes: Vec<(NodeID, Box<dyn Node>)> = selected
+            .iter()
+            .map(|id| (*id, self.nodes[id].clone()))
+            .collect();
+        nodes.sort_by_key(|(id, _)| id.0);
+
+        let edges = self
+            .edges
+            .iter()
+            .filter(|edge| selected.contains(&edge.0) && selected.contains(&edge.2))
+            .copied()
+            .collect();
+        let edge_gains = self
+            .edge_gains
+            .iter()
+            .filter(|(edge, _)| selected.contains(&edge.0) && selected.contains(&edge.2))
+            .map(|(edge, gain)| (*edge, *gain))
+            .collect();
+        let feedbacks = self
+            .feedbacks
+            .iter()
+            .filter(|feedback| selected.contains(&feedback.0) && selected.contains(&feedback.1))
+            .copied()
+            .collect();
+        let modulations = self
+            .modulations
+            .iter()
+            .filter(|modulation| {
+                selected.contains(&modulation.source) && selected.contains(&modulation.target)
+            })
+            .copied()
+            .collect();
+
+        GraphFragment {
+            nodes,
+            edges,
+            edge_gains,
+            feedbacks,
+            modulations,
+        }
+    }
+
+    /// Inserts clones of the nodes in the fragment with new IDs, and reconnects the edges, feedbacks and modulations among them.
+    /// Returns the map from the IDs in the fragment to the new IDs, to connect the pasted nodes to the rest of the graph.
+    /// The node names are not pasted, so they stay unique. The graph must be prepared again after pasting.
+    pub fn paste(&mut self, fragment: &GraphFragment) -> HashMap<NodeID, NodeID> {
+        let id_map: HashMap<NodeID, NodeID> = fragment
+            .nodes
+            .iter()
+            .map(|(old_id, node)| (*old_id, self.add_node(node.clone())))
+            .collect();
+
+        // The edges were valid in the original graph, and the nodes are the same
+        for (from, from_index, to, to_index) in &fragment.edges {
+            self.add_edge_unchecked((id_map[from], *from_index, id_map[to], *to_index));
+        }
+        for ((from, from_index, to, to_index), gain) in &fragment.edge_gains {
+            self.edge_gains
+                .insert((id_map[from], *from_index, id_map[to], *to_index), *gain);
+        }
+        for (send, ret) in &fragment.feedbacks {
+            self.feedbacks.push((id_map[send], id_map[ret]));
+        }
+        for modulation in &fragment.modulations {
+            self.modulations.push(Modulation {
+                source: id_map[&modulation.source],
+                target: id_map[&modulation.target],
+                ..*modulation
+            });
+        }
+
+        id_map
+    }
+}
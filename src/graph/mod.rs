@@ -1,22 +1,66 @@
+mod broadcast;
+mod edge_gain;
 pub mod error;
+mod graph_command;
+mod graph_fragment;
+mod modulation;
 pub mod node_id;
+mod saved_graph;
+mod solo;
+mod tap;
+mod timing;
 pub mod topological_sort;
 
+pub use graph_command::GraphCommand;
+pub use graph_fragment::GraphFragment;
+pub use modulation::Modulation;
+
+pub(crate) use saved_graph::SavedGraph;
+
 use crate::{
     data_types::AudioContext,
     graph::{error::GraphError, node_id::NodeID},
-    node::Node,
+    node::{
+        Node,
+        builtin::{FeedbackReturnNode, FeedbackSendNode},
+    },
 };
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// Whether the buffers of the graph are prepared for the current structure.
+/// A clone is not prepared, as its buffer pointers still point to the buffers of the original graph.
+#[derive(Default)]
+struct PreparedFlag(bool);
+
+impl Clone for PreparedFlag {
+    fn clone(&self) -> Self {
+        Self(false)
+    }
+}
 
 #[derive(Default, Clone)]
 pub struct Graph {
     // --- GRAPH STRUCTURE ---
     nodes: HashMap<NodeID, Box<dyn Node>>,
     edges: Vec<(NodeID, usize, NodeID, usize)>,
+    /// The gains of the edges which don't pass the signal at unity gain.
+    edge_gains: HashMap<(NodeID, usize, NodeID, usize), f32>,
     adjacency: HashMap<NodeID, Vec<NodeID>>,
     input_id: NodeID,
     output_id: NodeID,
+    /// Pairs of the feedback send and return nodes, which carry the audio to the next chunk.
+    feedbacks: Vec<(NodeID, NodeID)>,
+    /// Names given to the nodes, used to look up the nodes without tracking their IDs.
+    node_names: HashMap<NodeID, String>,
+    /// Routings from the node outputs to the node inputs, added on top of the edges.
+    modulations: Vec<Modulation>,
+    /// The node whose output replaces the input of the output node for auditioning.
+    soloed_node: Option<NodeID>,
+    /// The outputs whose audio can be read after processing, as the node ID and the output index.
+    taps: Vec<(NodeID, usize)>,
 
     // --- PROCESSING DATA ---
     sorted_nodes: Vec<NodeID>,
@@ -25,6 +69,26 @@ pub struct Graph {
     node_inputs: HashMap<NodeID, Vec<*const u8>>,
     node_outputs: HashMap<NodeID, Vec<*mut u8>>,
     zero_buffer: Vec<u8>,
+    /// Buffers of the inputs summing several edges or scaled by the edge gains, and the signals summed into them.
+    mixed_inputs: HashMap<(NodeID, usize), (Vec<u8>, edge_gain::MixSources)>,
+    /// Buffers of the inputs connected to mono outputs and the pointers to the mono signal.
+    broadcast_inputs: HashMap<(NodeID, usize), (Vec<u8>, *const u8)>,
+    /// Buffers of the modulated inputs and the pointers to the signal connected to them.
+    modulated_inputs: HashMap<(NodeID, usize), (Vec<u8>, *const u8)>,
+    /// The longest latency in samples from the input node to the output node.
+    latency: usize,
+    /// Whether `prepare` has been called since the nodes, the edges or the audio context last changed.
+    prepared: PreparedFlag,
+    /// The longest time a chunk may take to process before it is aborted, or None to never abort.
+    time_budget: Option<Duration>,
+    /// Whether to measure the time every node spends processing.
+    timing_enabled: bool,
+    /// The time every node has spent processing since the timings were last reset.
+    node_timings: HashMap<NodeID, Duration>,
+
+    // --- EDITING HISTORY ---
+    /// The inverses of the commands applied with `apply`, the latest last.
+    undo_stack: Vec<graph_command::UndoEntry>,
 
     // --- CONFIGURATIONS ---
     /// The current audio context.
@@ -76,6 +140,21 @@ impl Graph {
         &self.edges
     }
 
+    /// Returns the edges which start from the outputs of the node.
+    pub fn get_edges_from(&self, id: &NodeID) -> Vec<&(NodeID, usize, NodeID, usize)> {
+        self.edges.iter().filter(|edge| edge.0 == *id).collect()
+    }
+
+    /// Returns the edges which end at the inputs of the node.
+    pub fn get_edges_to(&self, id: &NodeID) -> Vec<&(NodeID, usize, NodeID, usize)> {
+        self.edges.iter().filter(|edge| edge.2 == *id).collect()
+    }
+
+    /// Returns whether the node's output is connected to another node's input.
+    pub fn is_connected(&self, edge: (NodeID, usize, NodeID, usize)) -> bool {
+        self.edges.contains(&edge)
+    }
+
     // --- NODE GETTING ---
 
     pub fn get_input_id(&self) -> NodeID {
@@ -102,6 +181,45 @@ impl Graph {
         self.nodes.get_mut(id)
     }
 
+    /// Returns the IDs of the nodes of the given type name, such as "ParametricEqNode", in ascending order.
+    pub fn get_nodes_of_type(&self, type_name: &str) -> Vec<NodeID> {
+        let mut ids: Vec<NodeID> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.get_type_name() == type_name)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    // --- NODE NAMING ---
+
+    /// Gives the node a name to look it up with `get_node_by_name`. Names don't have to be unique.
+    /// Returns an error if the node is not found.
+    pub fn set_node_name(&mut self, id: &NodeID, name: &str) -> Result<(), GraphError> {
+        if !self.nodes.contains_key(id) {
+            return Err(GraphError::NodeNotFound(*id));
+        }
+        self.node_names.insert(*id, name.to_string());
+        Ok(())
+    }
+
+    /// Returns the name of the node, or None if the node has no name.
+    pub fn get_node_name(&self, id: &NodeID) -> Option<&str> {
+        self.node_names.get(id).map(|name| name.as_str())
+    }
+
+    /// Returns the ID of the node with the given name.
+    /// If several nodes have the same name, returns the one with the smallest ID.
+    pub fn get_node_by_name(&self, name: &str) -> Option<NodeID> {
+        self.node_names
+            .iter()
+            .filter(|(_, node_name)| *node_name == name)
+            .map(|(id, _)| *id)
+            .min_by_key(|id| id.0)
+    }
+
     // --- NODE MANIPULATION ---
 
     pub fn set_input_id(&mut self, id: NodeID) {
@@ -119,6 +237,8 @@ impl Graph {
         node.update(&self.audio_ctx);
         // Insert the node to the map
         self.nodes.insert(id, node);
+        self.mark_unprepared();
+        self.clear_undo_history();
         id
     }
 
@@ -128,14 +248,158 @@ impl Graph {
         node.update(&self.audio_ctx);
         // Insert the node to the map
         self.nodes.insert(id, node);
+        self.mark_unprepared();
+        self.clear_undo_history();
+    }
+
+    /// Replaces the node with the new one, keeping the NodeID and the edges connected to the node, and returns the old node.
+    /// Returns an error and leaves the graph unchanged if the types of the new node don't match the existing edges.
+    /// The graph must be prepared again after replacing a node.
+    pub fn replace_node(
+        &mut self,
+        id: &NodeID,
+        mut node: Box<dyn Node>,
+    ) -> Result<Box<dyn Node>, GraphError> {
+        if !self.nodes.contains_key(id) {
+            return Err(GraphError::NodeNotFound(*id));
+        }
+        node.update(&self.audio_ctx);
+
+        // Check that the new node is compatible with the existing edges
+        for edge in self
+            .edges
+            .iter()
+            .filter(|edge| edge.0 == *id || edge.2 == *id)
+        {
+            let output_type = if edge.0 == *id {
+                node.get_output_type(edge.1)
+            } else {
+                self.nodes
+                    .get(&edge.0)
+                    .and_then(|n| n.get_output_type(edge.1))
+            }
+            .ok_or(GraphError::OutputTypeUnavailable(edge.0, edge.1))?;
+            let input_type = if edge.2 == *id {
+                node.get_input_type(edge.3)
+            } else {
+                self.nodes
+                    .get(&edge.2)
+                    .and_then(|n| n.get_input_type(edge.3))
+            }
+            .ok_or(GraphError::InputTypeUnavailable(edge.2, edge.3))?;
+
+            if output_type != input_type && !self.is_broadcast(output_type, input_type) {
+                return Err(GraphError::NodeTypeMismatch(*edge));
+            }
+        }
+
+        self.mark_unprepared();
+        self.clear_undo_history();
+        self.nodes
+            .insert(*id, node)
+            .ok_or(GraphError::NodeNotFound(*id))
+    }
+
+    /// Replaces the input node with the new one, keeping its NodeID and edges, and returns the old input node.
+    pub fn set_input_node(&mut self, node: Box<dyn Node>) -> Result<Box<dyn Node>, GraphError> {
+        let id = self.input_id;
+        self.replace_node(&id, node)
+    }
+
+    /// Replaces the output node with the new one, keeping its NodeID and edges, and returns the old output node.
+    pub fn set_output_node(&mut self, node: Box<dyn Node>) -> Result<Box<dyn Node>, GraphError> {
+        let id = self.output_id;
+        self.replace_node(&id, node)
     }
 
     /// Removes the node with the given NodeID from the graph.
     pub fn remove_node(&mut self, id: &NodeID) {
-        // Remove the edges connected to the node
+        // Remove the edges and the feedbacks connected to the node
         self.edges.retain(|edge| edge.0 != *id && edge.2 != *id);
-        // Remove the node
+        self.edge_gains
+            .retain(|edge, _| edge.0 != *id && edge.2 != *id);
+        self.feedbacks
+            .retain(|feedback| feedback.0 != *id && feedback.1 != *id);
+        self.modulations
+            .retain(|modulation| modulation.source != *id && modulation.target != *id);
+        // Remove the node and its name
         self.nodes.remove(id);
+        self.node_names.remove(id);
+        if self.soloed_node == Some(*id) {
+            self.soloed_node = None;
+        }
+        self.taps.retain(|tap| tap.0 != *id);
+        self.mark_unprepared();
+        self.clear_undo_history();
+    }
+
+    /// Removes the node and reconnects its upstream node to its downstream node, so the signal chain stays intact.
+    /// Only bridges when the first input and the first output of the node each have exactly one edge,
+    /// otherwise falls back to `remove_node`.
+    /// Returns the removed edges which could not be bridged.
+    pub fn remove_node_bridging(&mut self, id: &NodeID) -> Vec<(NodeID, usize, NodeID, usize)> {
+        let mut removed: Vec<(NodeID, usize, NodeID, usize)> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.0 == *id || edge.2 == *id)
+            .copied()
+            .collect();
+        self.remove_node(id);
+
+        // Find the edges connected to the primary input and output
+        let primary_inputs: Vec<_> = removed
+            .iter()
+            .filter(|edge| edge.2 == *id && edge.3 == 0)
+            .copied()
+            .collect();
+        let primary_outputs: Vec<_> = removed
+            .iter()
+            .filter(|edge| edge.0 == *id && edge.1 == 0)
+            .copied()
+            .collect();
+
+        if let ([upstream], [downstream]) = (primary_inputs.as_slice(), primary_outputs.as_slice())
+            && self
+                .add_edge((upstream.0, upstream.1, downstream.2, downstream.3))
+                .is_ok()
+        {
+            removed.retain(|edge| edge != upstream && edge != downstream);
+        }
+
+        removed
+    }
+
+    /// Adds a new node between the output of one node and the input of another, and returns the newly generated node ID.
+    /// The edge between them is replaced with edges through the first input and output of the new node.
+    /// If they are not connected, the new node is just connected in series.
+    /// Returns an error and leaves the graph unchanged if the types of the new node don't match.
+    pub fn insert_between(
+        &mut self,
+        node: Box<dyn Node>,
+        from: NodeID,
+        from_output: usize,
+        to: NodeID,
+        to_input: usize,
+    ) -> Result<NodeID, GraphError> {
+        let id = self.add_node(node);
+        let original_edge = (from, from_output, to, to_input);
+        let was_connected = self.remove_edge(original_edge).is_ok();
+
+        // Reconnect the nodes through the new node
+        let result = self
+            .add_edge((from, from_output, id, 0))
+            .and_then(|_| self.add_edge((id, 0, to, to_input)));
+
+        if let Err(err) = result {
+            // Restore the original connection
+            self.remove_node(&id);
+            if was_connected {
+                self.edges.push(original_edge);
+            }
+            return Err(err);
+        }
+
+        Ok(id)
     }
 
     // --- EDGE MANIPULATION ---
@@ -144,11 +408,24 @@ impl Graph {
     /// Useful for loading the graph from a file, where we assume the file is valid.
     pub fn add_edge_unchecked(&mut self, edge: (NodeID, usize, NodeID, usize)) {
         self.edges.push(edge);
+        self.mark_unprepared();
+        self.clear_undo_history();
     }
 
     /// Connects the node's output to another node's input, and returns an error if the type of the output and input are not the same, or if the node is not found.
+    /// A mono audio output, which holds one f32 per frame, can be connected to an audio input with the channels of the audio context.
+    /// The mono signal is then copied to every channel of the input, so a mono source plays equally on every channel.
+    /// The signals of several edges connected to the same input are summed.
     pub fn add_edge(&mut self, edge: (NodeID, usize, NodeID, usize)) -> Result<(), GraphError> {
-        // Check if the type of the output and input are the same
+        self.check_edge_type(&edge)?;
+        self.edges.push(edge);
+        self.mark_unprepared();
+        self.clear_undo_history();
+        Ok(())
+    }
+
+    /// Returns an error if the type of the output and input connected by the edge are not the same, or if the node is not found.
+    fn check_edge_type(&self, edge: &(NodeID, usize, NodeID, usize)) -> Result<(), GraphError> {
         let output_type = self
             .nodes
             .get(&edge.0)
@@ -160,13 +437,9 @@ impl Graph {
             .and_then(|node| node.get_input_type(edge.3))
             .ok_or(GraphError::InputTypeUnavailable(edge.2, edge.3))?;
 
-        if output_type != input_type {
-            return Err(GraphError::NodeTypeMismatch((
-                edge.0, edge.1, edge.2, edge.3,
-            )));
+        if output_type != input_type && !self.is_broadcast(output_type, input_type) {
+            return Err(GraphError::NodeTypeMismatch(*edge));
         }
-
-        self.edges.push(edge);
         Ok(())
     }
 
@@ -175,17 +448,78 @@ impl Graph {
     pub fn remove_edge(&mut self, edge: (NodeID, usize, NodeID, usize)) -> Result<(), GraphError> {
         if let Some(pos) = self.edges.iter().position(|e| *e == edge) {
             self.edges.remove(pos);
+            self.edge_gains.remove(&edge);
+            self.mark_unprepared();
+            self.clear_undo_history();
             Ok(())
         } else {
             Err(GraphError::EdgeNotFound(edge))
         }
     }
 
+    // --- FEEDBACK ---
+
+    /// Pairs a `FeedbackSendNode` with a `FeedbackReturnNode`, so the return node outputs the input of the send node.
+    /// The send and return nodes are not connected by an edge, which allows cyclic routing such as feedback delays.
+    /// Feedback introduces one chunk of latency, as the return node outputs the audio sent in the previous chunk.
+    /// Returns an error if the nodes are not a send and a return node.
+    pub fn add_feedback(&mut self, send: NodeID, ret: NodeID) -> Result<(), GraphError> {
+        let is_send = self
+            .nodes
+            .get(&send)
+            .ok_or(GraphError::NodeNotFound(send))?
+            .as_any()
+            .is::<FeedbackSendNode>();
+        let is_return = self
+            .nodes
+            .get(&ret)
+            .ok_or(GraphError::NodeNotFound(ret))?
+            .as_any()
+            .is::<FeedbackReturnNode>();
+        if !is_send || !is_return {
+            return Err(GraphError::InvalidFeedback(send, ret));
+        }
+
+        // Each return node receives the audio from only one send node
+        self.feedbacks.retain(|feedback| feedback.1 != ret);
+        self.feedbacks.push((send, ret));
+        Ok(())
+    }
+
+    /// Removes the pair of the feedback send and return nodes.
+    pub fn remove_feedback(&mut self, send: NodeID, ret: NodeID) {
+        self.feedbacks.retain(|feedback| *feedback != (send, ret));
+    }
+
+    /// Returns the pairs of the feedback send and return nodes.
+    pub fn get_feedbacks(&self) -> &[(NodeID, NodeID)] {
+        &self.feedbacks
+    }
+
+    /// Copies the input of every feedback send node to the output of the paired return node.
+    fn carry_feedbacks(&mut self) {
+        for (send, ret) in &self.feedbacks {
+            let (Some(input), Some(output), Some(output_type)) = (
+                self.node_inputs.get(send).and_then(|ptrs| ptrs.first()),
+                self.output_buffers.get_mut(&(*ret, 0)),
+                self.nodes.get(ret).and_then(|node| node.get_output_type(0)),
+            ) else {
+                continue;
+            };
+            let len = output_type.size.min(output.len());
+            // The input may point to the output itself, so copy with overlapping allowed
+            unsafe {
+                std::ptr::copy(*input, output.as_mut_ptr(), len);
+            }
+        }
+    }
+
     // --- AUDIO CONTEXT UPDATING ---
 
     /// Sets the audio context to the new one.
     pub fn set_audio_ctx(&mut self, audio_ctx: &AudioContext) {
         self.audio_ctx = audio_ctx.clone();
+        self.mark_unprepared();
 
         // Call update functions for every nodes
         for node in self.nodes.values_mut() {
@@ -193,6 +527,56 @@ impl Graph {
         }
     }
 
+    // --- LATENCY ---
+
+    /// Returns the longest latency in samples from the input node to the output node.
+    /// Calculated when the graph is prepared.
+    pub fn get_latency(&self) -> usize {
+        self.latency
+    }
+
+    /// Calculates the longest latency from the input node to the output node in the sorted order.
+    fn calculate_latency(&self) -> usize {
+        let node_latency = |id: &NodeID| self.nodes.get(id).map_or(0, |node| node.get_latency());
+
+        // Accumulate the latency of the paths reachable from the input node
+        let mut path_latencies: HashMap<NodeID, usize> = HashMap::new();
+        path_latencies.insert(self.input_id, node_latency(&self.input_id));
+        for node_id in self
+            .sorted_nodes
+            .iter()
+            .chain(std::iter::once(&self.output_id))
+        {
+            let upstream = self
+                .edges
+                .iter()
+                .filter(|edge| edge.2 == *node_id)
+                .filter_map(|edge| path_latencies.get(&edge.0))
+                .max()
+                .copied();
+            if let Some(upstream) = upstream {
+                path_latencies.insert(*node_id, upstream + node_latency(node_id));
+            }
+        }
+
+        path_latencies.get(&self.output_id).copied().unwrap_or(0)
+    }
+
+    // --- TIME BUDGET ---
+
+    /// Sets the longest time processing a chunk may take. Once a node finishes past the budget, the rest of
+    /// the chunk is skipped and processing returns `GraphError::NodeTimeout` with the node. The output is left
+    /// unwritten and the feedbacks are not carried. A node which never returns can't be interrupted, so the budget
+    /// protects the host from nodes which overrun, not from nodes which hang. Disabled with None, which is the default.
+    pub fn set_time_budget(&mut self, time_budget: Option<Duration>) {
+        self.time_budget = time_budget;
+    }
+
+    /// Returns the longest time processing a chunk may take.
+    pub fn get_time_budget(&self) -> Option<Duration> {
+        self.time_budget
+    }
+
     // --- GRAPH PROCESSING ---
 
     fn allocate_output_buffer(
@@ -227,11 +611,36 @@ impl Graph {
         Ok(())
     }
 
-    /// Prepares the graph for processing. The host must call this function before start processing, or it may lead to undefined behavior.
+    /// Returns whether the graph has been prepared since the nodes, the edges or the audio context last changed.
+    pub fn is_prepared(&self) -> bool {
+        self.prepared.0
+    }
+
+    /// Marks the graph as needing `prepare` before processing, after a change of the structure.
+    pub(super) fn mark_unprepared(&mut self) {
+        self.prepared.0 = false;
+    }
+
+    /// Prepares the graph for processing. The host must call this function before processing,
+    /// and again after changing the nodes, the edges, the modulations or the audio context.
+    /// Returns an error if an edge connects an output and an input of different types, such as buffers with different channel counts.
     pub fn prepare(&mut self) -> Result<(), GraphError> {
+        // Stay unprepared until every buffer is allocated
+        self.mark_unprepared();
+
         // First sort the graph
         self.sort_graph()?;
 
+        // Check the edges added without validation, so the nodes never read or write past the buffers
+        for edge in &self.edges {
+            self.check_edge_type(edge)?;
+        }
+
+        // Drop the buffers and pointers of the previous preparation, so no stale pointer survives
+        self.output_buffers.clear();
+        self.node_inputs.clear();
+        self.node_outputs.clear();
+
         // Allocate output buffer for the input node
         if let Some(input_node) = self.nodes.get_mut(&self.input_id) {
             Self::allocate_output_buffer(
@@ -246,7 +655,8 @@ impl Graph {
         for node_id in &self.sorted_nodes {
             if let Some(node) = self.nodes.get_mut(node_id) {
                 // Call prepare function for every nodes
-                node.prepare().map_err(GraphError::NodeError)?;
+                node.prepare()
+                    .map_err(|err| GraphError::NodeError(*node_id, err))?;
 
                 Self::allocate_output_buffer(
                     node_id,
@@ -258,6 +668,9 @@ impl Graph {
             }
         }
 
+        // Calculate the latency of the graph
+        self.latency = self.calculate_latency();
+
         // Calculate the max buffer size and create a zero buffer
         let mut max_size = 4usize;
         for (node_id, node) in &self.nodes {
@@ -300,57 +713,559 @@ impl Graph {
                 .or_insert_with(|| vec![zero_ptr; input_len]);
         }
 
+        // Redirect the mixed inputs, the inputs connected to mono outputs and the modulated inputs to their own buffers
+        self.prepare_mixes()?;
+        self.prepare_broadcasts()?;
+        self.prepare_modulations()?;
+
+        // Make room for the timings of the added nodes
+        if self.timing_enabled {
+            self.reserve_node_timings();
+        }
+
+        self.prepared.0 = true;
         Ok(())
     }
 
     /// Processes the graph in the sorted order and writes the result in the output pointer.
     /// The host must pass the audio context which is as the same as the one given in the `set_audio_ctx` function.
-    pub fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8]) {
-        // Get the pointer to the output buffer of the input node
-        let Some(output_buffers) = self.get_output_ptr(&self.input_id) else {
-            return;
-        };
-        let Some(input_node) = self.nodes.get_mut(&self.input_id) else {
-            return;
+    /// Returns an error if the graph has not been prepared since it last changed.
+    /// Every buffer is allocated in `prepare`, so processing doesn't allocate or lock as long as the nodes don't either.
+    pub fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8]) -> Result<(), GraphError> {
+        self.process_nodes(inputs, outputs, None)
+    }
+
+    /// Processes the graph like `process`, and returns a copy of the output buffer of every node for debugging.
+    /// The buffers are keyed by the node ID and the output index, and hold the raw bytes of the output type.
+    /// The buffers of the feedback return nodes already hold the audio for the next chunk.
+    /// The output of the output node is written to the output pointer as usual. Copying every buffer is slow,
+    /// so use `process` for playback.
+    pub fn process_traced(
+        &mut self,
+        inputs: &[*const u8],
+        outputs: &[*mut u8],
+    ) -> Result<HashMap<(NodeID, usize), Vec<u8>>, GraphError> {
+        self.process_nodes(inputs, outputs, None)?;
+
+        Ok(self
+            .output_buffers
+            .iter()
+            .map(|((node_id, index), buffer)| {
+                // Trim the buffer to the size of the output type
+                let size = self
+                    .nodes
+                    .get(node_id)
+                    .and_then(|node| node.get_output_type(*index))
+                    .map_or(buffer.len(), |output_type| {
+                        output_type.size.min(buffer.len())
+                    });
+                ((*node_id, *index), buffer[..size].to_vec())
+            })
+            .collect())
+    }
+
+    /// Processes only the nodes whose output may have changed since the last process call, which are
+    /// the nodes downstream of the dirty nodes, the time variant nodes, or the input node if the inputs changed.
+    /// The other nodes keep their outputs from the last process call, so the graph must have been
+    /// processed at least once after `prepare`. Collecting the changed nodes allocates, so use `process` on the audio thread.
+    pub fn process_from(
+        &mut self,
+        dirty_nodes: &[NodeID],
+        inputs: &[*const u8],
+        outputs: &[*mut u8],
+    ) -> Result<(), GraphError> {
+        // Collect the nodes whose output changes regardless of the dirty nodes
+        let input_changed = self.has_input_changed(inputs);
+        let mut stack: Vec<NodeID> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.is_time_variant())
+            .map(|(id, _)| *id)
+            .chain(dirty_nodes.iter().copied())
+            .chain(input_changed.then_some(self.input_id))
+            .collect();
+
+        // Mark every node downstream of them as changed
+        let mut changed: HashSet<NodeID> = HashSet::new();
+        while let Some(node_id) = stack.pop() {
+            if changed.insert(node_id) {
+                stack.extend(
+                    self.edges
+                        .iter()
+                        .filter(|edge| edge.0 == node_id)
+                        .map(|edge| edge.2),
+                );
+                stack.extend(
+                    self.modulations
+                        .iter()
+                        .filter(|modulation| modulation.source == node_id)
+                        .map(|modulation| modulation.target),
+                );
+            }
+        }
+
+        self.process_nodes(inputs, outputs, Some(&changed))
+    }
+
+    /// Returns whether the inputs differ from the outputs the input node passed on in the last process call.
+    /// The input node copies the inputs to its outputs, so an unchanged input leaves the graph unchanged.
+    fn has_input_changed(&self, inputs: &[*const u8]) -> bool {
+        let Some(input_node) = self.nodes.get(&self.input_id) else {
+            return true;
         };
+        inputs.iter().enumerate().any(|(index, input)| {
+            let (Some(output_type), Some(buffer)) = (
+                input_node.get_output_type(index),
+                self.output_buffers.get(&(self.input_id, index)),
+            ) else {
+                return true;
+            };
+            let size = output_type.size.min(buffer.len());
+            let input = unsafe { std::slice::from_raw_parts(*input, size) };
+            input != &buffer[..size]
+        })
+    }
+
+    /// Processes the nodes in the sorted order. Skips the nodes not contained in `changed` if given.
+    fn process_nodes(
+        &mut self,
+        inputs: &[*const u8],
+        outputs: &[*mut u8],
+        changed: Option<&HashSet<NodeID>>,
+    ) -> Result<(), GraphError> {
+        // Processing with stale buffers would read and write freed memory
+        if !self.prepared.0 {
+            return Err(GraphError::GraphNotPrepared);
+        }
+
+        // Start measuring the chunk against the time budget
+        let started = self.time_budget.map(|_| Instant::now());
+
+        // Borrow the pointer maps in place instead of cloning them to avoid allocations
+        // Get the pointer to the output buffer of the input node
+        let output_buffers = self
+            .node_outputs
+            .get(&self.input_id)
+            .ok_or(GraphError::NodeNotPrepared(self.input_id))?;
+        let input_node = self
+            .nodes
+            .get_mut(&self.input_id)
+            .ok_or(GraphError::NodeNotFound(self.input_id))?;
         // Process the input node
-        input_node.process(inputs, &output_buffers, &self.audio_ctx);
+        let node_started = self.timing_enabled.then(Instant::now);
+        input_node.process(inputs, output_buffers, &self.audio_ctx);
+        Self::record_node_timing(&self.input_id, node_started, &mut self.node_timings);
+
+        for node_id in &self.sorted_nodes {
+            // Keep the output of the last process call if the node is unchanged
+            if changed.is_some_and(|changed| !changed.contains(node_id)) {
+                continue;
+            }
+
+            // Mix the inputs, spread the mono inputs and add the modulations to the inputs of the node
+            Self::apply_mixes(node_id, self.audio_ctx.channels, &mut self.mixed_inputs);
+            Self::apply_broadcasts(
+                node_id,
+                self.audio_ctx.channels,
+                self.audio_ctx.buffer_size,
+                &mut self.broadcast_inputs,
+            );
+            Self::apply_modulations(
+                node_id,
+                &self.modulations,
+                &mut self.modulated_inputs,
+                &self.output_buffers,
+            );
 
-        for node_id in self.sorted_nodes.clone() {
             // Get the pointer to the input buffer of the node
-            let Some(input_buffers) = self.get_input_ptr(&node_id) else {
-                return;
-            };
+            let input_buffers = self
+                .node_inputs
+                .get(node_id)
+                .ok_or(GraphError::NodeNotPrepared(*node_id))?;
             // Get the pointer to the output buffer of the node
-            let Some(output_buffers) = self.get_output_ptr(&node_id) else {
-                return;
-            };
+            let output_buffers = self
+                .node_outputs
+                .get(node_id)
+                .ok_or(GraphError::NodeNotPrepared(*node_id))?;
 
             // Pass the pointers and process
-            if let Some(node) = self.nodes.get_mut(&node_id) {
-                node.process(&input_buffers, &output_buffers, &self.audio_ctx);
+            if let Some(node) = self.nodes.get_mut(node_id) {
+                let node_started = self.timing_enabled.then(Instant::now);
+                node.process(input_buffers, output_buffers, &self.audio_ctx);
+                Self::record_node_timing(node_id, node_started, &mut self.node_timings);
+            }
+
+            // Abort the chunk if the node made it run past the time budget
+            if let (Some(started), Some(time_budget)) = (started, self.time_budget) {
+                let elapsed = started.elapsed();
+                if elapsed > time_budget {
+                    return Err(GraphError::NodeTimeout(*node_id, elapsed));
+                }
             }
         }
 
         // Get the pointer to the input buffer of the output node
-        let Some(input_buffers) = self.get_input_ptr(&self.output_id) else {
-            return;
-        };
-        let Some(output_node) = self.nodes.get_mut(&self.output_id) else {
-            return;
+        Self::apply_mixes(
+            &self.output_id,
+            self.audio_ctx.channels,
+            &mut self.mixed_inputs,
+        );
+        Self::apply_broadcasts(
+            &self.output_id,
+            self.audio_ctx.channels,
+            self.audio_ctx.buffer_size,
+            &mut self.broadcast_inputs,
+        );
+        Self::apply_modulations(
+            &self.output_id,
+            &self.modulations,
+            &mut self.modulated_inputs,
+            &self.output_buffers,
+        );
+        let solo_ptr = self.get_solo_pointer();
+        let input_buffers = self
+            .node_inputs
+            .get_mut(&self.output_id)
+            .ok_or(GraphError::NodeNotPrepared(self.output_id))?;
+        let output_node = self
+            .nodes
+            .get_mut(&self.output_id)
+            .ok_or(GraphError::NodeNotFound(self.output_id))?;
+        // Swap the soloed output in for the first input while processing the output node
+        let connected_ptr = match (solo_ptr, input_buffers.first_mut()) {
+            (Some(solo_ptr), Some(first)) => Some(std::mem::replace(first, solo_ptr)),
+            _ => None,
         };
         // Process the output node
         // Output data will be written to the output pointer
-        output_node.process(&input_buffers, outputs, &self.audio_ctx);
-    }
+        let node_started = self.timing_enabled.then(Instant::now);
+        output_node.process(input_buffers, outputs, &self.audio_ctx);
+        Self::record_node_timing(&self.output_id, node_started, &mut self.node_timings);
+        if let (Some(connected_ptr), Some(first)) = (connected_ptr, input_buffers.first_mut()) {
+            *first = connected_ptr;
+        }
 
-    fn get_output_ptr(&self, from: &NodeID) -> Option<Vec<*mut u8>> {
-        self.node_outputs.get(from).cloned()
-    }
+        // Carry the feedback to the next chunk
+        self.carry_feedbacks();
 
-    fn get_input_ptr(&self, to: &NodeID) -> Option<Vec<*const u8>> {
-        self.node_inputs.get(to).cloned()
+        Ok(())
     }
 }
 
 unsafe impl Send for Graph {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_types::{TypeInfo, Voice},
+        node::builtin::{AudioInputNode, AudioOutputNode},
+    };
+    use std::any::Any;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A node which passes the audio, or the voices if `voices` is set, through and counts its process calls.
+    #[derive(Default, Clone)]
+    struct CountingNode {
+        data_type: TypeInfo,
+        calls: Arc<AtomicUsize>,
+        voices: bool,
+    }
+
+    impl Node for CountingNode {
+        fn clone_box(&self) -> Box<dyn Node> {
+            Box::new(self.clone())
+        }
+
+        fn get_input_names(&self) -> Vec<String> {
+            vec!["audio".to_string()]
+        }
+
+        fn get_output_names(&self) -> Vec<String> {
+            vec!["audio".to_string()]
+        }
+
+        fn get_output_len(&self) -> usize {
+            1
+        }
+
+        fn get_input_len(&self) -> usize {
+            1
+        }
+
+        fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+            (index == 0).then_some(&self.data_type)
+        }
+
+        fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+            (index == 0).then_some(&self.data_type)
+        }
+
+        fn update(&mut self, audio_ctx: &AudioContext) {
+            self.data_type = if self.voices {
+                TypeInfo::new(
+                    size_of::<Voice>() * audio_ctx.max_voices * audio_ctx.buffer_size,
+                    4,
+                )
+            } else {
+                TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4)
+            };
+        }
+
+        fn prepare(&mut self) -> Result<(), Box<dyn crate::graph::error::NodeError>> {
+            Ok(())
+        }
+
+        fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _: &AudioContext) {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            unsafe { std::ptr::copy_nonoverlapping(inputs[0], outputs[0], self.data_type.size) };
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn audio_ctx() -> AudioContext {
+        AudioContext {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: 4,
+            max_voices: 1,
+        }
+    }
+
+    /// Builds the chain input -> first -> second -> output, and returns the IDs and the call counters.
+    fn counting_chain() -> (Graph, [NodeID; 2], [Arc<AtomicUsize>; 2]) {
+        let mut graph = Graph::new(
+            Box::new(AudioInputNode::default()),
+            Box::new(AudioOutputNode::default()),
+            audio_ctx(),
+        );
+        let (input, output) = (graph.get_input_id(), graph.get_output_id());
+        let counters = [Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0))];
+        let ids = counters.clone().map(|calls| {
+            graph.add_node(Box::new(CountingNode {
+                calls,
+                ..Default::default()
+            }))
+        });
+        graph.add_edge((input, 0, ids[0], 0)).unwrap();
+        graph.add_edge((ids[0], 0, ids[1], 0)).unwrap();
+        graph.add_edge((ids[1], 0, output, 0)).unwrap();
+        graph.prepare().unwrap();
+        (graph, ids, counters)
+    }
+
+    fn calls(counters: &[Arc<AtomicUsize>; 2]) -> [usize; 2] {
+        counters
+            .each_ref()
+            .map(|calls| calls.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn process_from_skips_nodes_upstream_of_the_change() {
+        let (mut graph, ids, counters) = counting_chain();
+        let input = [0.5f32; 8];
+        let mut output = [0.0f32; 8];
+        let inputs = [input.as_ptr() as *const u8];
+        let outputs = [output.as_mut_ptr() as *mut u8];
+        graph.process(&inputs, &outputs).unwrap();
+        assert_eq!(calls(&counters), [1, 1]);
+
+        // Only the second node and the nodes after it are processed again
+        graph.process_from(&[ids[1]], &inputs, &outputs).unwrap();
+        assert_eq!(calls(&counters), [1, 2]);
+
+        // Nothing changed, so neither node is processed
+        graph.process_from(&[], &inputs, &outputs).unwrap();
+        assert_eq!(calls(&counters), [1, 2]);
+    }
+
+    #[test]
+    fn process_from_processes_the_chain_after_the_input_changes() {
+        let (mut graph, _, counters) = counting_chain();
+        let mut input = [0.5f32; 8];
+        let mut output = [0.0f32; 8];
+        let outputs = [output.as_mut_ptr() as *mut u8];
+        graph
+            .process(&[input.as_ptr() as *const u8], &outputs)
+            .unwrap();
+
+        input[3] = -0.5;
+        graph
+            .process_from(&[], &[input.as_ptr() as *const u8], &outputs)
+            .unwrap();
+        assert_eq!(calls(&counters), [2, 2]);
+    }
+
+    #[test]
+    fn process_before_prepare_errors() {
+        let mut graph = Graph::new(
+            Box::new(AudioInputNode::default()),
+            Box::new(AudioOutputNode::default()),
+            audio_ctx(),
+        );
+        let input = [0.5f32; 8];
+        let mut output = [0.0f32; 8];
+        let inputs = [input.as_ptr() as *const u8];
+        let outputs = [output.as_mut_ptr() as *mut u8];
+
+        let err = graph.process(&inputs, &outputs).unwrap_err();
+        assert!(matches!(err, GraphError::GraphNotPrepared));
+        assert!(err.to_string().contains("not been prepared"));
+        assert!(matches!(
+            graph.process_from(&[], &inputs, &outputs),
+            Err(GraphError::GraphNotPrepared)
+        ));
+
+        graph.prepare().unwrap();
+        assert!(graph.process(&inputs, &outputs).is_ok());
+    }
+
+    #[test]
+    fn changing_the_graph_requires_prepare() {
+        let (mut graph, ids, _) = counting_chain();
+        assert!(graph.is_prepared());
+
+        // A clone points to the buffers of the original graph
+        assert!(!graph.clone().is_prepared());
+
+        graph.remove_edge((ids[0], 0, ids[1], 0)).unwrap();
+        assert!(!graph.is_prepared());
+        graph.prepare().unwrap();
+
+        graph.set_audio_ctx(&AudioContext {
+            buffer_size: 8,
+            ..audio_ctx()
+        });
+        assert!(!graph.is_prepared());
+    }
+
+    #[test]
+    fn mixed_input_sums_the_scaled_edges() {
+        let mut graph = Graph::new(
+            Box::new(AudioInputNode::default()),
+            Box::new(AudioOutputNode::default()),
+            audio_ctx(),
+        );
+        let (input, output) = (graph.get_input_id(), graph.get_output_id());
+        let ids = [(); 2].map(|_| graph.add_node(Box::new(CountingNode::default())));
+        graph.add_edge((input, 0, ids[0], 0)).unwrap();
+        graph.add_edge((input, 0, ids[1], 0)).unwrap();
+        graph
+            .add_edge_with_gain((ids[0], 0, output, 0), 0.5)
+            .unwrap();
+        graph
+            .add_edge_with_gain((ids[1], 0, output, 0), 0.25)
+            .unwrap();
+        graph.prepare().unwrap();
+
+        // The mix buffer holds a single buffer of samples
+        let (buffer, _) = &graph.mixed_inputs[&(output, 0)];
+        assert_eq!(buffer.len(), 4 * 2 * 4);
+
+        let input_samples = [1.0f32; 8];
+        let mut output_samples = [0.0f32; 8];
+        graph
+            .process(
+                &[input_samples.as_ptr() as *const u8],
+                &[output_samples.as_mut_ptr() as *mut u8],
+            )
+            .unwrap();
+        assert_eq!(output_samples, [0.75; 8]);
+    }
+
+    #[test]
+    fn voices_cannot_be_mixed() {
+        let mut graph = Graph::new(
+            Box::new(AudioInputNode::default()),
+            Box::new(AudioOutputNode::default()),
+            audio_ctx(),
+        );
+        let ids = [(); 3].map(|_| {
+            graph.add_node(Box::new(CountingNode {
+                voices: true,
+                ..Default::default()
+            }))
+        });
+
+        // A gain can't scale the voices, and the edge is not added
+        assert!(matches!(
+            graph.add_edge_with_gain((ids[0], 0, ids[2], 0), 0.5),
+            Err(GraphError::InvalidMix(..))
+        ));
+        assert!(graph.get_edges().is_empty());
+
+        // Several edges can't be summed into the voices
+        graph.add_edge((ids[0], 0, ids[2], 0)).unwrap();
+        graph.add_edge((ids[1], 0, ids[2], 0)).unwrap();
+        assert!(matches!(graph.prepare(), Err(GraphError::InvalidMix(..))));
+    }
+
+    #[test]
+    fn modulation_adds_the_scaled_source() {
+        let (mut graph, ids, _) = counting_chain();
+        graph.add_modulation(ids[0], 0, ids[1], 0, 0.5).unwrap();
+        graph.prepare().unwrap();
+
+        // The modulated buffer holds a single buffer of samples
+        let (buffer, _) = &graph.modulated_inputs[&(ids[1], 0)];
+        assert_eq!(buffer.len(), 4 * 2 * 4);
+
+        let input = [1.0f32; 8];
+        let mut output = [0.0f32; 8];
+        graph
+            .process(
+                &[input.as_ptr() as *const u8],
+                &[output.as_mut_ptr() as *mut u8],
+            )
+            .unwrap();
+        assert_eq!(output, [1.5; 8]);
+    }
+
+    #[test]
+    fn undo_restores_the_gains_taps_and_solo_of_a_removed_node() {
+        let (mut graph, ids, _) = counting_chain();
+        let edge = (ids[0], 0, ids[1], 0);
+        graph.set_edge_gain(edge, 0.5).unwrap();
+        graph.add_tap(ids[1], 0).unwrap();
+        graph.solo_node(Some(ids[1])).unwrap();
+
+        graph.apply(GraphCommand::RemoveNode(ids[1])).unwrap();
+        assert_eq!(graph.get_edge_gain(&edge), 1.0);
+        assert!(graph.undo());
+        assert_eq!(graph.get_edge_gain(&edge), 0.5);
+        assert_eq!(graph.get_taps(), &[(ids[1], 0)]);
+        assert_eq!(graph.get_soloed_node(), Some(ids[1]));
+
+        graph.apply(GraphCommand::Disconnect(edge)).unwrap();
+        assert!(graph.undo());
+        assert_eq!(graph.get_edge_gain(&edge), 0.5);
+        graph.prepare().unwrap();
+    }
+
+    #[test]
+    fn direct_edits_clear_the_undo_history() {
+        let (mut graph, ids, _) = counting_chain();
+        graph
+            .apply(GraphCommand::Disconnect((ids[0], 0, ids[1], 0)))
+            .unwrap();
+        graph
+            .apply(GraphCommand::Connect((ids[0], 0, ids[1], 0)))
+            .unwrap();
+        assert!(graph.undo());
+        assert!(graph.can_undo());
+
+        // The recorded inverses may not apply after a direct edit
+        graph.add_node(Box::new(CountingNode::default()));
+        assert!(!graph.can_undo());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::graph::{Graph, error::GraphError, node_id::NodeID};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A routing from a node's output to another node's input, scaled by the depth.
+/// Modulations are separate from the edges, and are added on top of the signal the input receives.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Modulation {
+    pub source: NodeID,
+    pub source_output: usize,
+    pub target: NodeID,
+    pub target_input: usize,
+    pub depth: f32,
+}
+
+impl Graph {
+    // --- MODULATION ---
+
+    /// Routes the output of the source node to the input of the target node, scaled by the depth.
+    /// Before the target is processed in each chunk, the modulated input receives the signal connected to it,
+    /// or zeros, plus the sum of the modulations to it. The buffers are summed as f32 samples.
+    /// The target is processed after the source, so a modulation which forms a cycle makes `prepare` fail.
+    /// Replaces the depth if the same routing exists. Returns an error if the types of the output and input
+    /// don't match, if the node is not found, or if the source is the output node or the target is the input node.
+    /// The graph must be prepared again after adding a modulation.
+    pub fn add_modulation(
+        &mut self,
+        source: NodeID,
+        source_output: usize,
+        target: NodeID,
+        target_input: usize,
+        depth: f32,
+    ) -> Result<(), GraphError> {
+        // The output node has no output buffers and the input of the input node comes from the host
+        if source == self.output_id || target == self.input_id {
+            return Err(GraphError::InvalidModulation(target, target_input));
+        }
+        self.check_modulation_type(&(source, source_output, target, target_input))?;
+
+        self.remove_modulation(source, source_output, target, target_input);
+        self.modulations.push(Modulation {
+            source,
+            source_output,
+            target,
+            target_input,
+            depth,
+        });
+        self.mark_unprepared();
+        Ok(())
+    }
+
+    /// Removes the modulation from the output of the source node to the input of the target node.
+    pub fn remove_modulation(
+        &mut self,
+        source: NodeID,
+        source_output: usize,
+        target: NodeID,
+        target_input: usize,
+    ) {
+        self.modulations.retain(|modulation| {
+            (
+                modulation.source,
+                modulation.source_output,
+                modulation.target,
+                modulation.target_input,
+            ) != (source, source_output, target, target_input)
+        });
+        self.mark_unprepared();
+    }
+
+    /// Returns the modulations in the graph.
+    pub fn get_modulations(&self) -> &[Modulation] {
+        &self.modulations
+    }
+
+    /// Returns an error if the types of the output and the input of the modulation are not the same,
+    /// as the modulations are not broadcast like the edges.
+    fn check_modulation_type(
+        &self,
+        routing: &(NodeID, usize, NodeID, usize),
+    ) -> Result<(), GraphError> {
+        let output_type = self
+            .nodes
+            .get(&routing.0)
+            .and_then(|node| node.get_output_type(routing.1))
+            .ok_or(GraphError::OutputTypeUnavailable(routing.0, routing.1))?;
+        let input_type = self
+            .nodes
+            .get(&routing.2)
+            .and_then(|node| node.get_input_type(routing.3))
+            .ok_or(GraphError::InputTypeUnavailable(routing.2, routing.3))?;
+
+        if output_type != input_type {
+            return Err(GraphError::NodeTypeMismatch(*routing));
+        }
+        Ok(())
+    }
+
+    /// Allocates a buffer for every modulated input, and points the input to it instead of the connected signal.
+    /// Must be called after the inputs are connected in `prepare`.
+    pub(super) fn prepare_modulations(&mut self) -> Result<(), GraphError> {
+        self.modulated_inputs.clear();
+
+        for modulation in &self.modulations {
+            let source_edge = (
+                modulation.source,
+                modulation.source_output,
+                modulation.target,
+                modulation.target_input,
+            );
+            self.check_modulation_type(&source_edge)?;
+            if !self
+                .output_buffers
+                .contains_key(&(modulation.source, modulation.source_output))
+            {
+                return Err(GraphError::OutputBufferNotFound(
+                    modulation.source,
+                    modulation.source_output,
+                ));
+            }
+
+            let key = (modulation.target, modulation.target_input);
+            if self.modulated_inputs.contains_key(&key) {
+                continue;
+            }
+            let Some(input_ptr) = self
+                .node_inputs
+                .get_mut(&modulation.target)
+                .and_then(|ptrs| ptrs.get_mut(modulation.target_input))
+            else {
+                return Err(GraphError::NodeNotPrepared(modulation.target));
+            };
+            let size = self.nodes[&modulation.target]
+                .get_input_type(modulation.target_input)
+                .ok_or(GraphError::InputTypeUnavailable(
+                    modulation.target,
+                    modulation.target_input,
+                ))?
+                .size;
+
+            // Keep the connected signal, and let the node read the modulated copy.
+            // The size of the input type already covers the whole buffer
+            let buffer = vec![0u8; size];
+            let base = *input_ptr;
+            *input_ptr = buffer.as_ptr();
+            self.modulated_inputs.insert(key, (buffer, base));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the connected signal plus the modulations to the modulated inputs of the node.
+    pub(super) fn apply_modulations(
+        node_id: &NodeID,
+        modulations: &[Modulation],
+        modulated_inputs: &mut HashMap<(NodeID, usize), (Vec<u8>, *const u8)>,
+        output_buffers: &HashMap<(NodeID, usize), Vec<u8>>,
+    ) {
+        for ((target, input), (buffer, base)) in modulated_inputs.iter_mut() {
+            if target != node_id {
+                continue;
+            }
+            let len = buffer.len() / 4;
+            let samples =
+                unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut f32, len) };
+            // The connected buffer is at least as large as the input type
+            unsafe {
+                std::ptr::copy_nonoverlapping(*base as *const f32, samples.as_mut_ptr(), len);
+            }
+
+            for modulation in modulations
+                .iter()
+                .filter(|m| m.target == *target && m.target_input == *input)
+            {
+                let Some(source) =
+                    output_buffers.get(&(modulation.source, modulation.source_output))
+                else {
+                    continue;
+                };
+                let source = unsafe {
+                    std::slice::from_raw_parts(source.as_ptr() as *const f32, source.len() / 4)
+                };
+                for (sample, value) in samples.iter_mut().zip(source) {
+                    *sample += value * modulation.depth;
+                }
+            }
+        }
+    }
+}
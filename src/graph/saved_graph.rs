@@ -0,0 +1,115 @@
+use crate::{
+    data_types::AudioContext,
+    graph::{Graph, Modulation, node_id::NodeID},
+    mixer::ProjectFileError,
+    node::NodeRegistry,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A node saved as its type name and its parameters.
+#[derive(Clone, Serialize, Deserialize)]
+struct SavedNode {
+    type_name: String,
+    /// The preset exported from the node, or None if the node has no parameters.
+    preset: Option<Vec<u8>>,
+}
+
+/// The structure of a graph saved in a project file. The processing data and the editing history are not saved.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct SavedGraph {
+    nodes: Vec<(NodeID, SavedNode)>,
+    edges: Vec<(NodeID, usize, NodeID, usize)>,
+    #[serde(default)]
+    edge_gains: Vec<((NodeID, usize, NodeID, usize), f32)>,
+    input_id: NodeID,
+    output_id: NodeID,
+    feedbacks: Vec<(NodeID, NodeID)>,
+    node_names: HashMap<NodeID, String>,
+    modulations: Vec<Modulation>,
+    #[serde(default)]
+    taps: Vec<(NodeID, usize)>,
+    next_node_id: usize,
+}
+
+impl Graph {
+    // --- SAVING ---
+
+    /// Saves the structure of the graph and the parameters of the nodes.
+    pub(crate) fn to_saved(&self) -> SavedGraph {
+        let mut nodes: Vec<(NodeID, SavedNode)> = self
+            .nodes
+            .iter()
+            .map(|(id, node)| {
+                let saved_node = SavedNode {
+                    type_name: node.get_type_name().to_string(),
+                    preset: node.export_preset(),
+                };
+                (*id, saved_node)
+            })
+            .collect();
+        nodes.sort_by_key(|(id, _)| id.0);
+        let mut edge_gains: Vec<_> = self
+            .edge_gains
+            .iter()
+            .map(|(edge, gain)| (*edge, *gain))
+            .collect();
+        edge_gains.sort_by_key(|((from, from_index, to, to_index), _)| {
+            (from.0, *from_index, to.0, *to_index)
+        });
+
+        SavedGraph {
+            nodes,
+            edges: self.edges.clone(),
+            edge_gains,
+            input_id: self.input_id,
+            output_id: self.output_id,
+            feedbacks: self.feedbacks.clone(),
+            node_names: self.node_names.clone(),
+            modulations: self.modulations.clone(),
+            taps: self.taps.clone(),
+            next_node_id: self.next_node_id,
+        }
+    }
+
+    // --- LOADING ---
+
+    /// Restores the graph saved with `to_saved`, creating the nodes with the registry.
+    /// The graph must be prepared before processing.
+    pub(crate) fn from_saved(
+        saved: SavedGraph,
+        audio_ctx: &AudioContext,
+        registry: &NodeRegistry,
+    ) -> Result<Graph, ProjectFileError> {
+        let mut graph = Graph {
+            audio_ctx: audio_ctx.clone(),
+            ..Default::default()
+        };
+
+        for (id, saved_node) in saved.nodes {
+            let mut node = registry
+                .create(&saved_node.type_name)
+                .ok_or(ProjectFileError::UnknownNodeType(saved_node.type_name))?;
+            if let Some(preset) = saved_node.preset {
+                node.import_preset(&preset)
+                    .map_err(|err| ProjectFileError::PresetError(id, err))?;
+            }
+            graph.add_node_with_id(id, node);
+        }
+
+        // The saved graph was valid, so the edges and the routings are restored without validation
+        for edge in saved.edges {
+            graph.add_edge_unchecked(edge);
+        }
+        graph.edge_gains = saved.edge_gains.into_iter().collect();
+        graph.input_id = saved.input_id;
+        graph.output_id = saved.output_id;
+        graph.feedbacks = saved.feedbacks;
+        graph.node_names = saved.node_names;
+        graph.modulations = saved.modulations;
+        graph.taps = saved.taps;
+        graph.next_node_id = saved.next_node_id;
+
+        Ok(graph)
+    }
+}
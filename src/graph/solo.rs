@@ -0,0 +1,50 @@
+use crate::graph::{Graph, error::GraphError, node_id::NodeID};
+
+impl Graph {
+    // --- SOLO ---
+
+    /// Routes the first output of the node to the first input of the output node, bypassing the nodes after it,
+    /// so the node can be auditioned in isolation. Pass None to play the graph as connected again.
+    /// The edges are left untouched and every node keeps processing, so the tails keep ringing when the solo is cleared.
+    /// Returns an error if the node is not found or its first output doesn't have the type of the graph output.
+    pub fn solo_node(&mut self, id: Option<NodeID>) -> Result<(), GraphError> {
+        if let Some(id) = id {
+            let output_type = self
+                .nodes
+                .get(&id)
+                .ok_or(GraphError::NodeNotFound(id))?
+                .get_output_type(0)
+                .ok_or(GraphError::OutputTypeUnavailable(id, 0))?;
+            let input_type = self
+                .nodes
+                .get(&self.output_id)
+                .and_then(|node| node.get_input_type(0))
+                .ok_or(GraphError::InputTypeUnavailable(self.output_id, 0))?;
+            if output_type != input_type {
+                return Err(GraphError::NodeTypeMismatch((id, 0, self.output_id, 0)));
+            }
+        }
+        self.soloed_node = id;
+        Ok(())
+    }
+
+    /// Returns the node whose output is routed to the graph output, if any.
+    pub fn get_soloed_node(&self) -> Option<NodeID> {
+        self.soloed_node
+    }
+
+    /// Returns the pointer to the first output of the soloed node, or None if no node is soloed or
+    /// its output no longer has the type of the graph output.
+    pub(super) fn get_solo_pointer(&self) -> Option<*const u8> {
+        let id = self.soloed_node?;
+        let output_type = self.nodes.get(&id)?.get_output_type(0)?;
+        let input_type = self.nodes.get(&self.output_id)?.get_input_type(0)?;
+        if output_type != input_type {
+            return None;
+        }
+        self.node_outputs
+            .get(&id)
+            .and_then(|ptrs| ptrs.first())
+            .map(|ptr| *ptr as *const u8)
+    }
+}
@@ -0,0 +1,51 @@
+use crate::graph::{Graph, error::GraphError, node_id::NodeID};
+
+impl Graph {
+    // --- TAPS ---
+
+    /// Registers a tap on the output of the node, so its audio can be read with `get_tap` after every process call.
+    /// The mixer reads the taps to feed sends from a point inside the graph, such as after an equalizer.
+    /// Returns an error if the node is not found or the output is not an audio buffer with the channels of the audio context.
+    pub fn add_tap(&mut self, node: NodeID, output: usize) -> Result<(), GraphError> {
+        let output_type = self
+            .nodes
+            .get(&node)
+            .ok_or(GraphError::NodeNotFound(node))?
+            .get_output_type(output)
+            .ok_or(GraphError::OutputTypeUnavailable(node, output))?;
+        let audio_size = 4 * self.audio_ctx.channels * self.audio_ctx.buffer_size;
+        if output_type.size != audio_size || output_type.align != 4 {
+            return Err(GraphError::InvalidTap(node, output));
+        }
+
+        if !self.taps.contains(&(node, output)) {
+            self.taps.push((node, output));
+        }
+        Ok(())
+    }
+
+    /// Removes the tap from the output of the node.
+    pub fn remove_tap(&mut self, node: NodeID, output: usize) {
+        self.taps.retain(|tap| *tap != (node, output));
+    }
+
+    /// Returns the outputs with a tap, as the node ID and the output index.
+    pub fn get_taps(&self) -> &[(NodeID, usize)] {
+        &self.taps
+    }
+
+    /// Returns the interleaved audio of the tapped output from the last process call.
+    /// Returns None if no tap is registered on the output or the graph has not been prepared.
+    pub fn get_tap(&self, node: NodeID, output: usize) -> Option<&[f32]> {
+        if !self.prepared.0 || !self.taps.contains(&(node, output)) {
+            return None;
+        }
+        let buffer = self.output_buffers.get(&(node, output))?;
+        let len = self.audio_ctx.channels * self.audio_ctx.buffer_size;
+        if buffer.len() < len * 4 || buffer.as_ptr().align_offset(4) != 0 {
+            return None;
+        }
+        // The buffer holds at least `len` aligned f32 samples, as checked above
+        Some(unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const f32, len) })
+    }
+}
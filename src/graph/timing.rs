@@ -0,0 +1,61 @@
+use crate::graph::{Graph, node_id::NodeID};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+impl Graph {
+    // --- NODE TIMING ---
+
+    /// Sets whether to measure the time every node spends processing, for profiling the graph.
+    /// The times accumulate across process calls until `reset_node_timings`. Disabled by default,
+    /// as reading the clock around every node adds some overhead.
+    pub fn set_timing_enabled(&mut self, timing_enabled: bool) {
+        self.timing_enabled = timing_enabled;
+        if timing_enabled {
+            self.reserve_node_timings();
+        }
+    }
+
+    /// Returns whether the time every node spends processing is measured.
+    pub fn is_timing_enabled(&self) -> bool {
+        self.timing_enabled
+    }
+
+    /// Returns the time every node has spent processing since the timings were last reset.
+    /// The input and the output nodes are included.
+    pub fn get_node_timings(&self) -> &HashMap<NodeID, Duration> {
+        &self.node_timings
+    }
+
+    /// Resets the time every node has spent processing to zero.
+    pub fn reset_node_timings(&mut self) {
+        self.node_timings.clear();
+        if self.timing_enabled {
+            self.reserve_node_timings();
+        }
+    }
+
+    /// Inserts a timing for every node, so measuring the nodes while processing doesn't allocate.
+    /// Drops the timings of the removed nodes.
+    pub(super) fn reserve_node_timings(&mut self) {
+        self.node_timings
+            .retain(|id, _| self.nodes.contains_key(id));
+        for id in self.nodes.keys() {
+            self.node_timings.entry(*id).or_default();
+        }
+    }
+
+    /// Adds the time elapsed since the node started processing to its timing, if the node was measured.
+    pub(super) fn record_node_timing(
+        node_id: &NodeID,
+        started: Option<Instant>,
+        node_timings: &mut HashMap<NodeID, Duration>,
+    ) {
+        if let Some(started) = started
+            && let Some(timing) = node_timings.get_mut(node_id)
+        {
+            *timing += started.elapsed();
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::graph::{Graph, error::GraphError, node_id::NodeID};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 #[derive(PartialEq)]
 enum SortState {
@@ -17,6 +17,13 @@ impl Graph {
         for edge in &self.edges {
             self.adjacency.entry(edge.0).or_default().push(edge.2);
         }
+        // The modulation targets are processed after their sources
+        for modulation in &self.modulations {
+            self.adjacency
+                .entry(modulation.source)
+                .or_default()
+                .push(modulation.target);
+        }
 
         // Create visited and sorted array
         let mut states: HashMap<NodeID, SortState> = self
@@ -45,6 +52,57 @@ impl Graph {
         Ok(())
     }
 
+    /// Returns the layer of every node, which is the longest path from the nodes without incoming edges.
+    /// Nodes without incoming edges are in layer 0. Useful for laying out the nodes from left to right.
+    /// Returns error if a cycle is found.
+    pub fn compute_layers(&self) -> Result<HashMap<NodeID, usize>, GraphError> {
+        // Count the incoming edges of every node
+        let mut in_degrees: HashMap<NodeID, usize> = self.nodes.keys().map(|k| (*k, 0)).collect();
+        for edge in &self.edges {
+            if let Some(degree) = in_degrees.get_mut(&edge.2) {
+                *degree += 1;
+            }
+        }
+
+        // Start from the nodes without incoming edges
+        let mut layers: HashMap<NodeID, usize> = HashMap::new();
+        let mut queue: VecDeque<NodeID> = in_degrees
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &queue {
+            layers.insert(*id, 0);
+        }
+
+        // Visit the nodes in a topological order, pushing each downstream node after its upstream nodes
+        while let Some(node) = queue.pop_front() {
+            let layer = layers.get(&node).copied().unwrap_or(0);
+            for edge in self.edges.iter().filter(|edge| edge.0 == node) {
+                if let Some(degree) = in_degrees.get_mut(&edge.2) {
+                    let downstream = layers.entry(edge.2).or_insert(0);
+                    *downstream = (*downstream).max(layer + 1);
+                    *degree -= 1;
+                    if *degree == 0 {
+                        queue.push_back(edge.2);
+                    }
+                }
+            }
+        }
+
+        // Nodes with remaining incoming edges are in a cycle
+        if let Some(node) = in_degrees
+            .iter()
+            .filter(|(_, degree)| **degree > 0)
+            .map(|(id, _)| *id)
+            .min_by_key(|id| id.0)
+        {
+            return Err(GraphError::NodeCycle(node));
+        }
+
+        Ok(layers)
+    }
+
     /// Sorts the graph recursively using depth-first search algorithm.
     fn search_recursively(
         &mut self,
@@ -1,3 +1,4 @@
+pub mod audio_utils;
 pub mod data_types;
 pub mod graph;
 pub mod mixer;
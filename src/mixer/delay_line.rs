@@ -0,0 +1,43 @@
+/// Delays the interleaved audio by a fixed number of samples.
+#[derive(Clone, Default)]
+pub(super) struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    /// Creates a delay line which delays the audio by the given number of samples.
+    pub(super) fn new(delay: usize) -> Self {
+        Self {
+            buffer: vec![0.0; delay],
+            position: 0,
+        }
+    }
+
+    /// Delays the samples in place.
+    pub(super) fn process(&mut self, samples: &mut [f32]) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        // Swap the samples with the buffer in contiguous runs up to the end of the ring
+        let mut samples = samples;
+        while !samples.is_empty() {
+            let run = samples.len().min(self.buffer.len() - self.position);
+            let (head, rest) = samples.split_at_mut(run);
+            head.swap_with_slice(&mut self.buffer[self.position..self.position + run]);
+            self.position = (self.position + run) % self.buffer.len();
+            samples = rest;
+        }
+    }
+
+    /// Returns the number of the delayed samples.
+    pub(super) fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Clears the delayed samples.
+    pub(super) fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.position = 0;
+    }
+}
@@ -0,0 +1,86 @@
+/// Quantizes the audio to an integer bit depth, optionally applying TPDF dither and noise shaping.
+#[derive(Clone)]
+pub struct Dither {
+    /// The state of the random number generator.
+    seed: u32,
+    /// The quantization error of the last sample of each channel, fed back by the noise shaping.
+    errors: Vec<f32>,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self {
+            seed: 0x9E37_79B9,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Dither {
+    /// Returns a uniform random value between -0.5 and 0.5.
+    fn next_random(&mut self) -> f32 {
+        // Xorshift32
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 - 0.5
+    }
+
+    /// Quantizes the samples to the grid of the given bit depth, so that the host can convert them to integers without further rounding.
+    /// The dither is triangular noise with the amplitude of ±1 LSB added before rounding.
+    pub fn quantize(&mut self, samples: &mut [f32], bits: u32, dither: bool) {
+        let scale = (1u32 << (bits - 1)) as f32;
+        let max = (scale - 1.0) / scale;
+        for sample in samples {
+            let noise = if dither {
+                self.next_random() + self.next_random()
+            } else {
+                0.0
+            };
+            *sample = ((*sample * scale + noise).round() / scale).clamp(-1.0, max);
+        }
+    }
+
+    /// Clears the errors fed back by the noise shaping, keeping one for each channel.
+    /// Reallocates only when the channels exceed the previous ones, so the audio thread can reset the dither.
+    pub fn reset(&mut self, channels: usize) {
+        self.errors.clear();
+        self.errors.resize(channels, 0.0);
+    }
+
+    /// Quantizes the interleaved samples like `quantize`, subtracting the quantization error of the previous sample
+    /// in the same channel before rounding. This first-order noise shaping moves the quantization noise
+    /// towards high frequencies, where it is less audible. The errors are kept between calls, so consecutive
+    /// chunks are shaped continuously.
+    pub fn quantize_shaped(
+        &mut self,
+        samples: &mut [f32],
+        channels: usize,
+        bits: u32,
+        dither: bool,
+    ) {
+        let channels = channels.max(1);
+        if self.errors.len() != channels {
+            self.reset(channels);
+        }
+        let scale = (1u32 << (bits - 1)) as f32;
+        let max = (scale - 1.0) / scale;
+        // Take the errors out to generate the noise while updating them
+        let mut errors = std::mem::take(&mut self.errors);
+        for frame in samples.chunks_mut(channels) {
+            for (sample, error) in frame.iter_mut().zip(errors.iter_mut()) {
+                let noise = if dither {
+                    self.next_random() + self.next_random()
+                } else {
+                    0.0
+                };
+                let shaped = *sample * scale - *error;
+                let quantized = (shaped + noise).round().clamp(-scale, scale - 1.0);
+                // Limit the error so clipping doesn't accumulate into the next samples
+                *error = (quantized - shaped).clamp(-2.0, 2.0);
+                *sample = (quantized / scale).clamp(-1.0, max);
+            }
+        }
+        self.errors = errors;
+    }
+}
@@ -0,0 +1,14 @@
+use crate::data_types::BitDepth;
+use serde::{Deserialize, Serialize};
+
+/// How the output of the mixer is quantized for monitoring.
+/// The default keeps the float bit depth, which leaves the output unquantized.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DitherConfig {
+    /// The bit depth to quantize the output to.
+    pub bit_depth: BitDepth,
+    /// Whether to add TPDF dither before rounding.
+    pub dither: bool,
+    /// Whether to apply first-order noise shaping, which moves the quantization noise towards high frequencies.
+    pub noise_shaping: bool,
+}
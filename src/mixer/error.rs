@@ -0,0 +1,31 @@
+use crate::{
+    graph::{error::GraphError, node_id::NodeID},
+    mixer::TrackID,
+    node::error::PresetError,
+};
+
+#[derive(Debug)]
+pub enum ProjectError {
+    RoutingCycle(TrackID, TrackID),
+    TrackNotFound(TrackID),
+    NotABus(TrackID),
+    SendNotFound(TrackID, TrackID),
+}
+
+/// Errors occured while saving or loading a project file.
+#[derive(Debug)]
+pub enum ProjectFileError {
+    IoError(std::io::Error),
+    EncodeError(String),
+    DecodeError(String),
+    /// The file was saved in a format version which this engine can't read.
+    UnsupportedVersion(u32),
+    /// The file contains a node type which is not registered in the node registry.
+    UnknownNodeType(String),
+    PresetError(NodeID, PresetError),
+    /// The track is not one of the track types of the engine, so it can't be saved.
+    UnsupportedTrack(TrackID),
+    GraphError(GraphError),
+    /// The file contains a send or an output bus which the project rejects, such as a routing cycle.
+    InvalidRouting(ProjectError),
+}
@@ -1,16 +1,80 @@
+mod delay_line;
+mod dither;
+mod dither_config;
+mod error;
+mod monitor;
+mod playback_end_action;
 mod project;
+mod saved_project;
+mod summing_mode;
 mod tempo_event;
 mod tempo_map;
 mod track_id;
+mod track_send;
 
+pub use dither::Dither;
+pub use dither_config::DitherConfig;
+pub use error::{ProjectError, ProjectFileError};
+pub use monitor::Monitor;
+pub use playback_end_action::PlaybackEndAction;
 pub use project::Project;
+pub use summing_mode::SummingMode;
 pub use tempo_event::TempoEvent;
-pub use tempo_map::TempoMap;
+pub use tempo_map::{MIN_BPM, TempoMap};
 pub use track_id::TrackID;
+pub use track_send::TrackSend;
+
+use crate::{
+    audio_utils::{apply_gain, apply_gain_ramp, clamp_samples, mix_into, sanitize_samples},
+    graph::error::GraphError,
+    node::NodeRegistry,
+    track::bus_track::BusTrack,
+};
+use std::path::Path;
+
+/// The most errors kept from a process call, so that reporting them on the audio thread doesn't allocate.
+pub const MAX_RENDER_ERRORS: usize = 64;
 
 pub struct Mixer {
     // --- PROJECT ---
     pub project: Project,
+
+    // --- PROCESSING DATA ---
+    /// A buffer to render each track before summing it to the output.
+    track_buffer: Vec<f32>,
+    /// A buffer to copy the tapped audio of the sends into.
+    send_buffer: Vec<f32>,
+    /// A buffer to copy the audio of the monitored track into.
+    monitor_buffer: Vec<f32>,
+
+    // --- SUMMING ---
+    /// The summing gain reached at the end of the last buffer, which the next buffer ramps from.
+    /// None after seeking, as the audio before doesn't continue into the next buffer.
+    summing_gain: Option<f32>,
+
+    // --- OUTPUT DITHERING ---
+    /// How the output is quantized for monitoring.
+    output_dither: DitherConfig,
+    /// The state of the dither applied to the output.
+    dither: Dither,
+
+    // --- OUTPUT SANITIZING ---
+    /// Whether to replace the NaN and infinite samples of the output with silence.
+    sanitize_output: bool,
+    /// The number of samples replaced since the count was last taken.
+    sanitized_samples: usize,
+
+    // --- MONITORING ---
+    /// The track replacing the output for auditioning, or None to output the whole mix.
+    monitor: Option<Monitor>,
+
+    // --- PULL RENDERING ---
+    /// The frame which `next_chunk` renders next, moved by seeking.
+    playhead: usize,
+
+    // --- ERRORS ---
+    /// Errors occured while rendering the tracks in the last process call, up to `MAX_RENDER_ERRORS`.
+    render_errors: Vec<(TrackID, GraphError)>,
 }
 
 impl Mixer {
@@ -18,43 +82,440 @@ impl Mixer {
 
     /// Creates a new mixer instance with the given project.
     pub fn new(project: Project) -> Self {
-        Self { project }
+        let playhead = project.tempo_map.beats_to_samples(project.range_start);
+        let mut mixer = Self {
+            project,
+            track_buffer: Vec::new(),
+            send_buffer: Vec::new(),
+            monitor_buffer: Vec::new(),
+            summing_gain: None,
+            output_dither: DitherConfig::default(),
+            dither: Dither::default(),
+            sanitize_output: false,
+            sanitized_samples: 0,
+            monitor: None,
+            playhead,
+            render_errors: Vec::with_capacity(MAX_RENDER_ERRORS),
+        };
+        mixer.dither.reset(mixer.project.audio_ctx.channels);
+        mixer
     }
 
     // --- PROJECT APPLYING ---
 
-    /// Replaces the project with the new one. Tracks inside the project must have been prepared.
-    pub fn apply_project(&mut self, new_project: Project, playhead: usize) {
-        self.project = new_project;
+    /// Replaces the project with the new one, and returns the old project. Tracks inside the project must have been prepared.
+    /// The audio thread can pass the old project to another thread, so freeing it doesn't block the audio.
+    pub fn apply_project(&mut self, new_project: Project, playhead: usize) -> Project {
+        let old_project = std::mem::replace(&mut self.project, new_project);
         self.seek(playhead);
+        old_project
+    }
+
+    // --- PROJECT FILES ---
+
+    /// Saves the project of the mixer to the file at the given path.
+    pub fn save_project(&self, path: impl AsRef<Path>) -> Result<(), ProjectFileError> {
+        self.project.save(path)
+    }
+
+    /// Loads the project from the file at the given path and prepares it, then plays it from the beginning.
+    /// The registry must have every node type used in the file. The current project is kept if loading fails.
+    pub fn load_project(
+        &mut self,
+        path: impl AsRef<Path>,
+        registry: &NodeRegistry,
+    ) -> Result<(), ProjectFileError> {
+        let mut project = Project::load(path, registry)?;
+        project.prepare().map_err(ProjectFileError::GraphError)?;
+        self.apply_project(project, 0);
+        Ok(())
+    }
+
+    // --- SUMMING MODE ---
+
+    /// Sets how the tracks are summed to the output.
+    pub fn set_summing_mode(&mut self, summing_mode: SummingMode) {
+        self.project.summing_mode = summing_mode;
+    }
+
+    // --- OUTPUT DITHERING ---
+
+    /// Sets how the output is quantized, so monitoring sounds like the audio exported at the bit depth.
+    /// Disabled by default. The export renders with its own mixer, so the output dither doesn't affect the export.
+    pub fn set_output_dither(&mut self, config: DitherConfig) {
+        self.output_dither = config;
+        self.dither.reset(self.project.audio_ctx.channels);
+    }
+
+    /// Returns how the output is quantized.
+    pub fn get_output_dither(&self) -> DitherConfig {
+        self.output_dither
+    }
+
+    // --- OUTPUT SANITIZING ---
+
+    /// Sets whether to replace the NaN and infinite samples of the output with silence before clamping,
+    /// so a misbehaving node can't blow out the output. Disabled by default.
+    pub fn set_sanitize_output(&mut self, sanitize_output: bool) {
+        self.sanitize_output = sanitize_output;
+    }
+
+    /// Returns whether the NaN and infinite samples of the output are replaced with silence.
+    pub fn get_sanitize_output(&self) -> bool {
+        self.sanitize_output
+    }
+
+    /// Takes the number of the samples replaced since the last call, for debugging the nodes producing them.
+    pub fn take_sanitized_count(&mut self) -> usize {
+        std::mem::take(&mut self.sanitized_samples)
+    }
+
+    // --- MONITORING ---
+
+    /// Sets the track whose audio replaces the output, or None to output the whole mix.
+    /// Every track is still processed, so the buses receive the sends of the other tracks.
+    /// The tracks have no faders of their own, so the post-fader monitor follows the gain the summing mode applies to the mix.
+    /// The export renders with its own mixer, so the monitor doesn't affect the export.
+    pub fn set_monitor(&mut self, monitor: Option<Monitor>) {
+        self.monitor = monitor;
+    }
+
+    /// Returns the track whose audio replaces the output.
+    pub fn get_monitor(&self) -> Option<Monitor> {
+        self.monitor
     }
 
     // --- SEEKING ---
 
-    /// Tells every tracks that the it will seek.
+    /// Tells every tracks that the it will seek, and moves the playhead of `next_chunk`.
     pub fn seek(&mut self, playhead: usize) {
+        self.playhead = playhead;
+        self.summing_gain = None;
         for track in self.project.tracks.values_mut() {
             track.seek(playhead);
         }
+        // Clear the delayed audio from before seeking
+        for delay_line in self.project.delay_lines.values_mut() {
+            delay_line.clear();
+        }
+        for delay_line in self.project.send_delay_lines.values_mut() {
+            delay_line.clear();
+        }
+    }
+
+    // --- LATENCY COMPENSATION ---
+
+    /// Returns the latency of the track in samples. The project must have been prepared.
+    pub fn get_track_latency(&self, id: &TrackID) -> Option<usize> {
+        self.project
+            .tracks
+            .get(id)
+            .map(|track| track.get_graph().get_latency())
+    }
+
+    // --- ERROR GETTING ---
+
+    /// Takes the errors occured while rendering the tracks in the last process call.
+    /// The errors are drained without freeing the buffer, so this is safe to call on the audio thread.
+    pub fn take_render_errors(&mut self) -> std::vec::Drain<'_, (TrackID, GraphError)> {
+        self.render_errors.drain(..)
+    }
+
+    // --- PULL RENDERING ---
+
+    /// Renders the chunk at the playhead and advances the playhead, for hosts which pull the audio themselves.
+    /// Returns the interleaved samples of a buffer, shortened to the end of the range for the last chunk,
+    /// or None once the playhead reaches the end of the range. The project must have been prepared.
+    /// Allocates the returned chunk, so use `process` on the audio thread.
+    pub fn next_chunk(&mut self) -> Option<Vec<f32>> {
+        let end = self.project.get_range_end_samples();
+        let buffer_size = self.project.audio_ctx.buffer_size;
+        let channels = self.project.audio_ctx.channels;
+        if self.playhead >= end || buffer_size == 0 {
+            return None;
+        }
+
+        let mut chunk = vec![0.0; buffer_size * channels];
+        self.process(true, self.playhead, &mut chunk);
+
+        let frames = (end - self.playhead).min(buffer_size);
+        chunk.truncate(frames * channels);
+        self.playhead += frames;
+        Some(chunk)
+    }
+
+    /// Rewinds the playhead of `next_chunk` to the start of the range.
+    pub fn reset(&mut self) {
+        self.seek(
+            self.project
+                .tempo_map
+                .beats_to_samples(self.project.range_start),
+        );
+    }
+
+    /// Returns the frame which `next_chunk` renders next.
+    pub fn get_playhead(&self) -> usize {
+        self.playhead
     }
 
     // --- MIXING PROCESS ---
 
     /// Processes the tracks in the mixer at the specified playhead.
+    /// Doesn't allocate unless the audio context has changed, so it is safe to call on the audio thread.
     pub fn process(&mut self, is_playing: bool, playhead: usize, output: &mut [f32]) {
+        let len = self.project.audio_ctx.buffer_size * self.project.audio_ctx.channels;
+
         // Fill the output buffer with zeros before processing
-        unsafe {
-            let len = self.project.audio_ctx.buffer_size * self.project.audio_ctx.channels;
-            let dst = std::slice::from_raw_parts_mut(output.as_mut_ptr(), len);
-            dst.fill(0.0);
+        output[..len].fill(0.0);
+
+        // Resize the track buffer if the audio context has changed
+        if self.track_buffer.len() != len {
+            self.track_buffer = vec![0.0; len];
+            self.send_buffer = vec![0.0; len];
+            self.monitor_buffer = vec![0.0; len];
         }
 
-        // Call process function for every tracks
-        for track in self.project.tracks.values_mut() {
-            track.process(is_playing, playhead, output);
+        // Clear the errors from the previous process call
+        self.render_errors.clear();
+
+        // Clear the audio of the monitored track from the previous process call
+        if self.monitor.is_some() {
+            self.monitor_buffer.fill(0.0);
+        }
+
+        // Count the tracks producing audio for the summing mode
+        let mut active_tracks = 0;
+
+        // Call process function for every tracks, sources before the buses they send to
+        for track_id in &self.project.process_order {
+            let Some(track) = self.project.tracks.get_mut(track_id) else {
+                continue;
+            };
+
+            // Render the track into the track buffer
+            self.track_buffer.fill(0.0);
+            if let Err(err) = track.process(is_playing, playhead, &mut self.track_buffer)
+                && self.render_errors.len() < MAX_RENDER_ERRORS
+            {
+                self.render_errors.push((*track_id, err));
+            }
+
+            // Pass the track audio to the buses, each send delayed to align it at its bus
+            for send in self.project.sends.get(track_id).into_iter().flatten() {
+                // Copy the tapped audio out of the source track before borrowing the bus
+                if let Some((node, output)) = send.tap {
+                    let Some(tapped) = self
+                        .project
+                        .tracks
+                        .get(track_id)
+                        .and_then(|track| track.get_graph().get_tap(node, output))
+                    else {
+                        continue;
+                    };
+                    let copy_len = tapped.len().min(len);
+                    self.send_buffer[..copy_len].copy_from_slice(&tapped[..copy_len]);
+                } else {
+                    self.send_buffer.copy_from_slice(&self.track_buffer);
+                }
+                if let Some(delay_line) = self
+                    .project
+                    .send_delay_lines
+                    .get_mut(&(*track_id, send.target))
+                {
+                    delay_line.process(&mut self.send_buffer);
+                }
+
+                if let Some(bus) = self
+                    .project
+                    .tracks
+                    .get_mut(&send.target)
+                    .and_then(|track| track.as_any_mut().downcast_mut::<BusTrack>())
+                {
+                    bus.pass_audio(&self.send_buffer, send.level);
+                }
+            }
+
+            // Delay the track to align it with the other tracks at its output
+            if let Some(delay_line) = self.project.delay_lines.get_mut(track_id) {
+                delay_line.process(&mut self.track_buffer);
+            }
+
+            // Keep the audio of the monitored track to replace the output
+            if self
+                .monitor
+                .is_some_and(|monitor| monitor.track == *track_id)
+            {
+                self.monitor_buffer.copy_from_slice(&self.track_buffer);
+            }
+
+            // Sum the track to its output bus, or to the output if the track has no bus
+            let output_bus = self
+                .project
+                .output_buses
+                .get(track_id)
+                .and_then(|bus_id| self.project.tracks.get_mut(bus_id))
+                .and_then(|track| track.as_any_mut().downcast_mut::<BusTrack>());
+            if let Some(bus) = output_bus {
+                bus.pass_audio(&self.track_buffer, 1.0);
+            } else {
+                if self.track_buffer.iter().any(|s| *s != 0.0) {
+                    active_tracks += 1;
+                }
+                mix_into(output, &self.track_buffer, 1.0);
+            }
+        }
+
+        // Replace the output with the monitored track, which is silent if the track is not found
+        if self.monitor.is_some() {
+            output[..len].copy_from_slice(&self.monitor_buffer);
+        }
+
+        // Scale the sum according to the summing mode
+        // The gain changes with the number of active tracks, so ramp it across the buffer
+        let gain = match self.monitor {
+            Some(monitor) if monitor.pre_fader => 1.0,
+            _ => self.project.summing_mode.get_gain(active_tracks),
+        };
+        match self.summing_gain.replace(gain) {
+            Some(previous) if previous != gain => {
+                let channels = self.project.audio_ctx.channels;
+                apply_gain_ramp(&mut output[..len], channels, previous, gain);
+            }
+            _ if gain != 1.0 => apply_gain(&mut output[..len], gain),
+            _ => {}
+        }
+
+        // Silence the NaN and infinite samples, which clamping would let through
+        if self.sanitize_output {
+            self.sanitized_samples += sanitize_samples(&mut output[..len]);
         }
 
         // Clamp the output between -1.0 and 1.0 for safety
-        output.iter_mut().for_each(|s| *s = s.clamp(-1.0, 1.0))
+        clamp_samples(output);
+
+        // Quantize the output to the monitoring bit depth
+        if let Some(bits) = self.output_dither.bit_depth.integer_bits() {
+            let channels = self.project.audio_ctx.channels;
+            let dither = self.output_dither.dither;
+            if self.output_dither.noise_shaping {
+                self.dither
+                    .quantize_shaped(&mut output[..len], channels, bits, dither);
+            } else {
+                self.dither.quantize(&mut output[..len], bits, dither);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_types::{AudioContext, Beats},
+        node::builtin::CompressorNode,
+        track::{
+            Track,
+            audio_track::{AudioRegion, AudioTrack},
+        },
+    };
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Whether the allocations of the thread are counted.
+        static COUNTING: Cell<bool> = const { Cell::new(false) };
+        /// The number of allocations, reallocations and frees counted on the thread.
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// The system allocator, which counts the calls made on the threads counting them.
+    struct CountingAllocator;
+
+    impl CountingAllocator {
+        fn count() {
+            if COUNTING.try_with(Cell::get).unwrap_or(false) {
+                let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            }
+        }
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            Self::count();
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            Self::count();
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            Self::count();
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Returns the number of allocations the closure made on this thread.
+    fn count_allocations(f: impl FnOnce()) -> usize {
+        ALLOCATIONS.with(|count| count.set(0));
+        COUNTING.with(|counting| counting.set(true));
+        f();
+        COUNTING.with(|counting| counting.set(false));
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    fn audio_ctx() -> AudioContext {
+        AudioContext {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: 64,
+            max_voices: 1,
+        }
+    }
+
+    #[test]
+    fn process_does_not_allocate() {
+        let audio_ctx = audio_ctx();
+        let mut project = Project::new(audio_ctx.clone(), 120.0, Beats(0.0), Beats(4.0));
+
+        // An audio track sending to a bus with a compressor, grouped into another bus
+        let mut track = AudioTrack::new(audio_ctx.clone());
+        track.add_region(AudioRegion::from_planar(
+            &[vec![0.5; 48000], vec![-0.5; 48000]],
+            48000,
+            120.0,
+            Beats(0.0),
+        ));
+        let track = project.add_track(Box::new(track));
+        let mut bus = BusTrack::new(audio_ctx.clone());
+        let graph = bus.get_graph_mut();
+        let (input, output) = (graph.get_input_id(), graph.get_output_id());
+        graph.remove_edge((input, 0, output, 0)).unwrap();
+        let compressor = graph.add_node(Box::new(CompressorNode::default()));
+        graph.add_edge((input, 0, compressor, 0)).unwrap();
+        graph.add_edge((compressor, 0, output, 0)).unwrap();
+        let bus = project.add_track(Box::new(bus));
+        let group = project.add_track(Box::new(BusTrack::new(audio_ctx.clone())));
+        project.add_send(track, bus, 0.5).unwrap();
+        project.set_output_bus(track, Some(group)).unwrap();
+        project.prepare().unwrap();
+
+        let mut mixer = Mixer::new(project);
+        mixer.set_sanitize_output(true);
+        let mut output = vec![0.0; audio_ctx.buffer_size * audio_ctx.channels];
+        // The first call sizes the buffers of the mixer to the audio context
+        mixer.process(true, 0, &mut output);
+
+        let allocations = count_allocations(|| {
+            for chunk in 1..64 {
+                mixer.process(true, chunk * audio_ctx.buffer_size, &mut output);
+            }
+        });
+        assert_eq!(allocations, 0);
+        assert!(output.iter().any(|sample| *sample != 0.0));
     }
 }
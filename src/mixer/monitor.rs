@@ -0,0 +1,11 @@
+use crate::mixer::TrackID;
+use serde::{Deserialize, Serialize};
+
+/// A track auditioned in isolation on the output of the mixer, without changing the rendered mix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Monitor {
+    /// The track whose audio replaces the output.
+    pub track: TrackID,
+    /// Whether to listen before the gain of the summing mode (PFL), instead of at the level it reaches the mix (AFL).
+    pub pre_fader: bool,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Determines what the playback does when the playhead reaches the end of the range.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaybackEndAction {
+    /// Pauses the playback, leaving the playhead at the end of the range.
+    #[default]
+    Stop,
+    /// Jumps back to the start of the range and keeps playing, for gapless looping.
+    Loop,
+    /// Keeps playing with the playhead held at the end of the range, so live input still sounds.
+    /// The tracks are processed as not playing while held, so the last chunk is not rendered again.
+    Hold,
+}
@@ -1,16 +1,43 @@
 use crate::{
-    data_types::{AudioContext, Beats},
-    graph::error::GraphError,
-    mixer::{TempoMap, track_id::TrackID},
-    track::Track,
+    data_types::{AudioContext, Beats, BitDepth},
+    graph::{error::GraphError, node_id::NodeID},
+    mixer::{
+        PlaybackEndAction, ProjectError, ProjectFileError, SummingMode, TempoMap, TrackSend,
+        delay_line::DelayLine,
+        saved_project::{PROJECT_FILE_VERSION, SavedProject},
+        track_id::TrackID,
+    },
+    node::NodeRegistry,
+    track::{SavedTrack, Track, bus_track::BusTrack},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
 };
-use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct Project {
     // --- TRACKS ---
     /// Tracks in the project.
     pub tracks: HashMap<TrackID, Box<dyn Track>>,
+    /// The order of the tracks shown to the user. Doesn't affect the processing.
+    track_order: Vec<TrackID>,
+
+    // --- ROUTING ---
+    /// Sends from the source track to the bus tracks.
+    pub(super) sends: HashMap<TrackID, Vec<TrackSend>>,
+    /// The bus tracks which receive the main output of the tracks instead of the master output.
+    pub(super) output_buses: HashMap<TrackID, TrackID>,
+    /// The order to process the tracks, where the source tracks come before the buses.
+    pub(super) process_order: Vec<TrackID>,
+    /// How the tracks are summed to the output.
+    pub summing_mode: SummingMode,
+
+    // --- LATENCY COMPENSATION ---
+    /// Delays applied to the main outputs of the tracks to align every path to the output, allocated in `prepare`.
+    pub(super) delay_lines: HashMap<TrackID, DelayLine>,
+    /// Delays applied to the sends to align them at the bus they go to, keyed by the source and the target.
+    pub(super) send_delay_lines: HashMap<(TrackID, TrackID), DelayLine>,
 
     // --- TEMPO MAP ---
     /// A tempo map to store the tempo changes.
@@ -25,6 +52,14 @@ pub struct Project {
     pub range_start: Beats,
     /// The duration of the range to be exported or played.
     pub range_duration: Beats,
+    /// What the playback does when the playhead reaches the end of the range.
+    pub playback_end_action: PlaybackEndAction,
+
+    // --- EXPORT ---
+    /// The bit depth which the exported audio is quantized to.
+    pub export_bit_depth: BitDepth,
+    /// Whether to apply dither before quantizing the exported audio to an integer bit depth.
+    pub export_dither: bool,
 
     // --- MISCS ---
     /// The next track ID for generating track IDs.
@@ -43,10 +78,20 @@ impl Project {
     ) -> Self {
         Self {
             tracks: HashMap::new(),
+            track_order: Vec::new(),
+            sends: HashMap::new(),
+            output_buses: HashMap::new(),
+            process_order: Vec::new(),
+            summing_mode: SummingMode::default(),
+            delay_lines: HashMap::new(),
+            send_delay_lines: HashMap::new(),
             tempo_map: TempoMap::new(audio_ctx.clone(), bpm),
             audio_ctx,
             range_start,
             range_duration,
+            playback_end_action: PlaybackEndAction::default(),
+            export_bit_depth: BitDepth::default(),
+            export_dither: false,
             next_track_id: 0,
         }
     }
@@ -60,14 +105,61 @@ impl Project {
     ) -> Self {
         Self {
             tracks: HashMap::new(),
+            track_order: Vec::new(),
+            sends: HashMap::new(),
+            output_buses: HashMap::new(),
+            process_order: Vec::new(),
+            summing_mode: SummingMode::default(),
+            delay_lines: HashMap::new(),
+            send_delay_lines: HashMap::new(),
             tempo_map,
             audio_ctx,
             range_start,
             range_duration,
+            playback_end_action: PlaybackEndAction::default(),
+            export_bit_depth: BitDepth::default(),
+            export_dither: false,
             next_track_id: 0,
         }
     }
 
+    // --- TEMPO ---
+
+    /// Replaces the tempo map of the project, letting each track follow the new tempo.
+    /// The regions locked to the tempo keep their beats, and the other regions keep their time in seconds.
+    /// The project must be prepared again for the new tempo.
+    pub fn set_tempo_map(&mut self, mut tempo_map: TempoMap) {
+        tempo_map.set_audio_ctx(self.audio_ctx.clone());
+        for track in self.tracks.values_mut() {
+            track.on_tempo_changed(&self.tempo_map, &tempo_map);
+        }
+        self.tempo_map = tempo_map;
+    }
+
+    // --- RANGE ---
+
+    /// Sets the range to be exported or played between the start and the end beats.
+    /// The project must be prepared again for the new range.
+    pub fn set_range(&mut self, start: Beats, end: Beats) {
+        self.range_start = start;
+        self.range_duration = (end - start).max(Beats(0.0));
+    }
+
+    /// Returns the end of the range in samples.
+    pub fn get_range_end_samples(&self) -> usize {
+        self.tempo_map
+            .beats_to_samples(self.range_start + self.range_duration)
+    }
+
+    // --- EXPORT SETTINGS ---
+
+    /// Sets the bit depth of the exported audio.
+    /// Enables dither for 16-bit and disables it for the other bit depths.
+    pub fn set_export_bit_depth(&mut self, bit_depth: BitDepth) {
+        self.export_bit_depth = bit_depth;
+        self.export_dither = bit_depth == BitDepth::Int16;
+    }
+
     // --- TRACK ID GENERATION ---
 
     /// Sets the next track ID for generating track IDs.
@@ -89,12 +181,21 @@ impl Project {
         let id = self.generate_track_id();
         track.set_audio_ctx(&self.audio_ctx);
         self.tracks.insert(id, track);
+        self.track_order.push(id);
         id
     }
 
-    /// Removes the track from the mixer.
+    /// Removes the track from the mixer, along with the sends from and to the track.
+    /// The tracks which output to the removed track output to the master output again.
     pub fn remove_track(&mut self, id: &TrackID) {
         self.tracks.remove(id);
+        self.track_order.retain(|track_id| track_id != id);
+        self.sends.remove(id);
+        for sends in self.sends.values_mut() {
+            sends.retain(|send| send.target != *id);
+        }
+        self.output_buses
+            .retain(|source, bus| source != id && bus != id);
     }
 
     /// Returns a reference to the track.
@@ -107,20 +208,630 @@ impl Project {
         self.tracks.get_mut(id)
     }
 
+    // --- TRACK ORDER ---
+
+    /// Moves the track to the given index in the track order. Indices past the end move the track to the end.
+    /// Returns an error if the track is not found.
+    pub fn move_track(&mut self, id: &TrackID, new_index: usize) -> Result<(), ProjectError> {
+        if !self.tracks.contains_key(id) {
+            return Err(ProjectError::TrackNotFound(*id));
+        }
+        let mut order = self.get_track_order();
+        order.retain(|track_id| track_id != id);
+        order.insert(new_index.min(order.len()), *id);
+        self.track_order = order;
+        Ok(())
+    }
+
+    /// Returns the IDs of the tracks in the order shown to the user.
+    /// Tracks inserted into `tracks` directly come last, in the order of their IDs.
+    pub fn get_track_order(&self) -> Vec<TrackID> {
+        let mut order: Vec<TrackID> = self
+            .track_order
+            .iter()
+            .filter(|id| self.tracks.contains_key(id))
+            .copied()
+            .collect();
+        let mut rest: Vec<TrackID> = self
+            .tracks
+            .keys()
+            .filter(|id| !order.contains(id))
+            .copied()
+            .collect();
+        rest.sort_by_key(|id| id.0);
+        order.extend(rest);
+        order
+    }
+
+    // --- ROUTING ---
+
+    /// Sends the output of the source track to the target bus track with the given level.
+    /// Overwrites the level if the send already exists. The sends to a missing or non-bus track are kept but ignored.
+    /// Returns an error if the send creates a routing cycle.
+    pub fn add_send(
+        &mut self,
+        source: TrackID,
+        target: TrackID,
+        level: f32,
+    ) -> Result<(), ProjectError> {
+        // Reject the send if the target already routes to the source
+        if source == target || self.is_routed(target, source) {
+            return Err(ProjectError::RoutingCycle(source, target));
+        }
+
+        let sends = self.sends.entry(source).or_default();
+        if let Some(send) = sends.iter_mut().find(|send| send.target == target) {
+            send.level = level;
+        } else {
+            sends.push(TrackSend::new(target, level));
+        }
+        Ok(())
+    }
+
+    /// Sends the audio of a tapped node output in the graph of the source track instead of the track output,
+    /// such as a pre-fader point for a reverb send. None sends the track output again.
+    /// The tap must be registered with `Graph::add_tap`, otherwise the send is silent.
+    /// Returns an error if the send doesn't exist.
+    pub fn set_send_tap(
+        &mut self,
+        source: &TrackID,
+        target: &TrackID,
+        tap: Option<(NodeID, usize)>,
+    ) -> Result<(), ProjectError> {
+        let send = self
+            .sends
+            .get_mut(source)
+            .and_then(|sends| sends.iter_mut().find(|send| send.target == *target))
+            .ok_or(ProjectError::SendNotFound(*source, *target))?;
+        send.tap = tap;
+        Ok(())
+    }
+
+    /// Removes the send from the source track to the target track.
+    pub fn remove_send(&mut self, source: &TrackID, target: &TrackID) {
+        if let Some(sends) = self.sends.get_mut(source) {
+            sends.retain(|send| send.target != *target);
+        }
+    }
+
+    /// Returns the sends of the track.
+    pub fn get_sends(&self, source: &TrackID) -> &[TrackSend] {
+        self.sends.get(source).map_or(&[], |sends| sends.as_slice())
+    }
+
+    /// Routes the main output of the track to the bus track instead of the master output, such as to group drum tracks.
+    /// None routes the track to the master output, which is the default.
+    /// Returns an error if a track is not found, if the target is not a bus track, or if the routing creates a cycle.
+    pub fn set_output_bus(
+        &mut self,
+        source: TrackID,
+        bus: Option<TrackID>,
+    ) -> Result<(), ProjectError> {
+        if !self.tracks.contains_key(&source) {
+            return Err(ProjectError::TrackNotFound(source));
+        }
+        let Some(bus) = bus else {
+            self.output_buses.remove(&source);
+            return Ok(());
+        };
+
+        let is_bus = self
+            .tracks
+            .get(&bus)
+            .ok_or(ProjectError::TrackNotFound(bus))?
+            .as_any()
+            .is::<BusTrack>();
+        if !is_bus {
+            return Err(ProjectError::NotABus(bus));
+        }
+        // Reject the routing if the bus already routes to the source
+        if source == bus || self.is_routed(bus, source) {
+            return Err(ProjectError::RoutingCycle(source, bus));
+        }
+
+        self.output_buses.insert(source, bus);
+        Ok(())
+    }
+
+    /// Returns the bus track which receives the main output of the track, or None if it goes to the master output.
+    pub fn get_output_bus(&self, source: &TrackID) -> Option<TrackID> {
+        self.output_buses.get(source).copied()
+    }
+
+    /// Returns the tracks which the track routes its audio to, through the sends and the output bus.
+    fn get_routes(&self, source: &TrackID) -> impl Iterator<Item = TrackID> {
+        self.get_sends(source)
+            .iter()
+            .map(|send| send.target)
+            .chain(self.get_output_bus(source))
+    }
+
+    /// Returns whether the audio of the source track reaches the target track through the sends and the output buses.
+    fn is_routed(&self, source: TrackID, target: TrackID) -> bool {
+        let mut stack = vec![source];
+        let mut visited = HashSet::new();
+        while let Some(track_id) = stack.pop() {
+            if track_id == target {
+                return true;
+            }
+            if visited.insert(track_id) {
+                stack.extend(self.get_routes(&track_id));
+            }
+        }
+        false
+    }
+
+    /// Sorts the tracks so that every track is processed before the buses it sends or outputs to.
+    /// The routing cycles are rejected when the sends and the output buses are set, so every track is sorted.
+    fn sort_tracks(&mut self) {
+        // Collect the sends between existing tracks, ignoring the sends to non-bus tracks
+        let mut routes: HashMap<TrackID, Vec<TrackID>> = HashMap::new();
+        let mut in_degrees: HashMap<TrackID, usize> =
+            self.tracks.keys().map(|id| (*id, 0)).collect();
+        for (source, sends) in &self.sends {
+            if !self.tracks.contains_key(source) {
+                continue;
+            }
+            for send in sends {
+                let is_bus = self
+                    .tracks
+                    .get(&send.target)
+                    .is_some_and(|track| track.as_any().is::<BusTrack>());
+                if !is_bus {
+                    continue;
+                }
+                routes.entry(*source).or_default().push(send.target);
+                *in_degrees.entry(send.target).or_default() += 1;
+            }
+        }
+        // The output buses are checked to be bus tracks when they are set
+        for (source, bus) in &self.output_buses {
+            if !self.tracks.contains_key(source) || !self.tracks.contains_key(bus) {
+                continue;
+            }
+            routes.entry(*source).or_default().push(*bus);
+            *in_degrees.entry(*bus).or_default() += 1;
+        }
+
+        // Process the tracks without incoming sends first, in the order of their IDs
+        let mut ready: Vec<TrackID> = in_degrees
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        ready.sort_by(|a, b| b.cmp(a));
+        self.process_order.clear();
+        while let Some(track_id) = ready.pop() {
+            self.process_order.push(track_id);
+            for target in routes.get(&track_id).into_iter().flatten() {
+                if let Some(degree) = in_degrees.get_mut(target) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push(*target);
+                    }
+                }
+            }
+        }
+
+        // Process the tracks left in a cycle last rather than dropping them
+        if self.process_order.len() < self.tracks.len() {
+            let mut remaining: Vec<TrackID> = in_degrees
+                .into_iter()
+                .filter(|(_, degree)| *degree > 0)
+                .map(|(id, _)| id)
+                .collect();
+            remaining.sort();
+            self.process_order.extend(remaining);
+        }
+    }
+
+    // --- SAVING ---
+
+    /// Saves the project to the file at the given path.
+    /// Returns an error if a track is not one of the track types of the engine.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ProjectFileError> {
+        let bytes = self.to_bytes()?;
+        std::fs::write(path, bytes).map_err(ProjectFileError::IoError)
+    }
+
+    /// Serializes the project into the bytes of a project file, which `from_bytes` reads back.
+    /// Tracks are saved with their graphs, regions and settings, and nodes with their presets.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProjectFileError> {
+        let mut tracks = Vec::new();
+        for (id, track) in &self.tracks {
+            let saved_track =
+                SavedTrack::from_track(&**track).ok_or(ProjectFileError::UnsupportedTrack(*id))?;
+            tracks.push((*id, saved_track));
+        }
+        tracks.sort_by_key(|(id, _)| *id);
+
+        let saved = SavedProject {
+            version: PROJECT_FILE_VERSION,
+            audio_ctx: self.audio_ctx.clone(),
+            tempo_events: self.tempo_map.events.clone(),
+            tracks,
+            track_order: self.get_track_order(),
+            sends: self.sends.clone(),
+            output_buses: self.output_buses.clone(),
+            summing_mode: self.summing_mode,
+            range_start: self.range_start,
+            range_duration: self.range_duration,
+            playback_end_action: self.playback_end_action,
+            export_bit_depth: self.export_bit_depth,
+            export_dither: self.export_dither,
+            next_track_id: self.next_track_id,
+        };
+        rmp_serde::to_vec_named(&saved)
+            .map_err(|err| ProjectFileError::EncodeError(err.to_string()))
+    }
+
+    // --- LOADING ---
+
+    /// Loads the project from the file at the given path, creating the nodes with the registry.
+    /// The project must be prepared before processing.
+    pub fn load(path: impl AsRef<Path>, registry: &NodeRegistry) -> Result<Self, ProjectFileError> {
+        let bytes = std::fs::read(path).map_err(ProjectFileError::IoError)?;
+        Self::from_bytes(&bytes, registry)
+    }
+
+    /// Deserializes the project from the bytes returned by `to_bytes`, creating the nodes with the registry.
+    /// The project must be prepared before processing.
+    pub fn from_bytes(bytes: &[u8], registry: &NodeRegistry) -> Result<Self, ProjectFileError> {
+        let saved: SavedProject = rmp_serde::from_slice(bytes)
+            .map_err(|err| ProjectFileError::DecodeError(err.to_string()))?;
+        if saved.version != PROJECT_FILE_VERSION {
+            return Err(ProjectFileError::UnsupportedVersion(saved.version));
+        }
+
+        // Add the tempo events one by one, which clamps their BPM and calculates their sample offsets
+        let mut tempo_map = TempoMap::default();
+        tempo_map.set_audio_ctx(saved.audio_ctx.clone());
+        for event in saved.tempo_events {
+            tempo_map.add_event(event);
+        }
+        // The conversions between beats and samples need an event at the beginning
+        if tempo_map
+            .events
+            .first()
+            .is_none_or(|event| event.beat != Beats(0.0))
+        {
+            return Err(ProjectFileError::DecodeError(
+                "The tempo map has no event at beat 0".to_string(),
+            ));
+        }
+
+        let mut project = Self::with_tempo_map(
+            saved.audio_ctx,
+            tempo_map,
+            saved.range_start,
+            saved.range_duration,
+        );
+        for (id, saved_track) in saved.tracks {
+            let track = saved_track.into_track(&project.audio_ctx, registry)?;
+            project.tracks.insert(id, track);
+        }
+        project.track_order = saved.track_order;
+
+        // Route the tracks again, which rejects the routing cycles and the routes to missing tracks
+        for (source, bus) in saved.output_buses {
+            project
+                .set_output_bus(source, Some(bus))
+                .map_err(ProjectFileError::InvalidRouting)?;
+        }
+        for (source, sends) in saved.sends {
+            for send in sends {
+                project
+                    .add_send(source, send.target, send.level)
+                    .and_then(|_| project.set_send_tap(&source, &send.target, send.tap))
+                    .map_err(ProjectFileError::InvalidRouting)?;
+            }
+        }
+        project.summing_mode = saved.summing_mode;
+        project.playback_end_action = saved.playback_end_action;
+        project.export_bit_depth = saved.export_bit_depth;
+        project.export_dither = saved.export_dither;
+        project.next_track_id = saved.next_track_id;
+
+        Ok(project)
+    }
+
     // --- MIXING PREPARATION ---
 
     /// Prepares the tracks in the mixer for the playback.
-    /// `start` and `duration` indicates the range to be processed.
+    /// The tracks are rendered from the beginning of the timeline to the end of the range,
+    /// so the playhead can be placed before the range start.
     pub fn prepare(&mut self) -> Result<(), GraphError> {
-        // Convert the start and duration beats to samples
-        let start_samples = self.tempo_map.beats_to_samples(self.range_start);
-        let duration_samples = self.tempo_map.beats_to_samples(self.range_duration);
+        self.prepare_from(0)
+    }
+
+    /// Prepares the tracks in the mixer for exporting the range.
+    /// The tracks are only rendered from the range start, so the playhead must not be placed before it.
+    pub fn prepare_for_export(&mut self) -> Result<(), GraphError> {
+        self.prepare_from(self.tempo_map.beats_to_samples(self.range_start))
+    }
+
+    /// Prepares the tracks to be processed from the given sample to the end of the range.
+    fn prepare_from(&mut self, start_samples: usize) -> Result<(), GraphError> {
+        // Convert the end beats to samples
+        // The end is measured on the tempo map, as the tempo may change inside the range
+        let end_samples = self.get_range_end_samples();
+        let duration_samples = end_samples.saturating_sub(start_samples);
+
+        // Sort the tracks by their routing
+        self.sort_tracks();
 
         // Prepare the tracks one by one
         for track in self.tracks.values_mut() {
             track.prepare(start_samples, duration_samples, &self.tempo_map)?;
         }
 
+        // Allocate the delay lines here, as the project is prepared off the audio thread
+        self.compensate_latency();
+
         Ok(())
     }
+
+    // --- LATENCY COMPENSATION ---
+
+    /// Returns the delay in samples applied to the main output of the track to align it with the other tracks.
+    /// The project must have been prepared.
+    pub fn get_track_delay(&self, id: &TrackID) -> Option<usize> {
+        self.delay_lines
+            .get(id)
+            .map(|delay_line| delay_line.len() / self.audio_ctx.channels.max(1))
+    }
+
+    /// Creates the delay lines which align the tracks at every bus and at the output.
+    /// The inputs of a bus are aligned to the latest track routed or sent to it, and the bus latency adds up
+    /// on the way to the output. The sends are delayed separately, as the bus they go to may be aligned differently.
+    /// A tapped send is compensated with the latency of the whole track.
+    fn compensate_latency(&mut self) {
+        let channels = self.audio_ctx.channels;
+        let track_latency = |id: &TrackID| {
+            self.tracks
+                .get(id)
+                .map_or(0, |track| track.get_graph().get_latency())
+        };
+        // The routes to missing or non-bus tracks are ignored by the mixer
+        let is_bus = |id: &&TrackID| {
+            self.tracks
+                .get(id)
+                .is_some_and(|track| track.as_any().is::<BusTrack>())
+        };
+
+        // Calculate the latency at which the inputs of each bus are aligned, sources before the buses
+        let mut input_latencies: HashMap<TrackID, usize> = HashMap::new();
+        for id in &self.process_order {
+            let arrival = input_latencies.get(id).copied().unwrap_or(0) + track_latency(id);
+            let targets = self
+                .output_buses
+                .get(id)
+                .into_iter()
+                .chain(
+                    self.sends
+                        .get(id)
+                        .into_iter()
+                        .flatten()
+                        .map(|send| &send.target),
+                )
+                .filter(is_bus);
+            for target in targets {
+                let input_latency = input_latencies.entry(*target).or_insert(0);
+                *input_latency = (*input_latency).max(arrival);
+            }
+        }
+
+        // The latency of the audio when it leaves the track, before the delay
+        let arrival =
+            |id: &TrackID| input_latencies.get(id).copied().unwrap_or(0) + track_latency(id);
+        let output_bus = |id: &TrackID| self.output_buses.get(id).filter(is_bus);
+        let output_latency = self
+            .process_order
+            .iter()
+            .filter(|id| output_bus(id).is_none())
+            .map(arrival)
+            .max()
+            .unwrap_or(0);
+        // The latency at which the audio arrives at the bus, or at the output for None
+        let aligned = |bus: Option<&TrackID>| {
+            bus.map_or(output_latency, |bus| {
+                input_latencies.get(bus).copied().unwrap_or(0)
+            })
+        };
+
+        let delay_lines = self
+            .process_order
+            .iter()
+            .map(|id| {
+                let delay = aligned(output_bus(id)) - arrival(id);
+                (*id, DelayLine::new(delay * channels))
+            })
+            .collect();
+        let send_delay_lines = self
+            .process_order
+            .iter()
+            .flat_map(|id| {
+                self.sends
+                    .get(id)
+                    .into_iter()
+                    .flatten()
+                    .map(move |send| (id, send))
+            })
+            .filter(|(_, send)| is_bus(&&send.target))
+            .map(|(id, send)| {
+                let delay = aligned(Some(&send.target)) - arrival(id);
+                ((*id, send.target), DelayLine::new(delay * channels))
+            })
+            .collect();
+        self.delay_lines = delay_lines;
+        self.send_delay_lines = send_delay_lines;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mixer::MIN_BPM,
+        node::builtin::CompressorNode,
+        track::audio_track::{AudioRegion, AudioTrack},
+    };
+
+    fn audio_ctx() -> AudioContext {
+        AudioContext {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: 64,
+            max_voices: 1,
+        }
+    }
+
+    /// Returns a bus track which passes the audio through a compressor with the given lookahead.
+    fn bus_with_lookahead(lookahead_ms: f32) -> Box<dyn Track> {
+        let mut bus = BusTrack::new(audio_ctx());
+        let graph = bus.get_graph_mut();
+        let (input, output) = (graph.get_input_id(), graph.get_output_id());
+        graph.remove_edge((input, 0, output, 0)).unwrap();
+
+        let mut compressor = CompressorNode::default();
+        compressor.set_lookahead(lookahead_ms);
+        let compressor = graph.add_node(Box::new(compressor));
+        graph.add_edge((input, 0, compressor, 0)).unwrap();
+        graph.add_edge((compressor, 0, output, 0)).unwrap();
+        Box::new(bus)
+    }
+
+    #[test]
+    fn latency_is_compensated_through_buses_and_sends() {
+        let mut project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let direct = project.add_track(bus_with_lookahead(1.0));
+        let sender = project.add_track(bus_with_lookahead(0.0));
+        let grouped = project.add_track(bus_with_lookahead(1.0));
+        let group = project.add_track(bus_with_lookahead(2.0));
+        project.set_output_bus(grouped, Some(group)).unwrap();
+        project.add_send(sender, group, 1.0).unwrap();
+        project.prepare().unwrap();
+
+        // The group receives the grouped track 48 samples late and adds 96 samples, 144 at the output
+        assert_eq!(project.get_track_delay(&direct), Some(96));
+        assert_eq!(project.get_track_delay(&sender), Some(144));
+        assert_eq!(project.get_track_delay(&grouped), Some(0));
+        assert_eq!(project.get_track_delay(&group), Some(0));
+        // The send is aligned with the grouped track at the input of the group
+        let send_delay = &project.send_delay_lines[&(sender, group)];
+        assert_eq!(send_delay.len(), 48 * 2);
+    }
+
+    /// Saves the project, lets the closure edit the saved contents, and loads the project again.
+    fn reload_edited(
+        project: &Project,
+        edit: impl FnOnce(&mut SavedProject),
+    ) -> Result<Project, ProjectFileError> {
+        let mut saved: SavedProject = rmp_serde::from_slice(&project.to_bytes().unwrap()).unwrap();
+        edit(&mut saved);
+        Project::from_bytes(&rmp_serde::to_vec(&saved).unwrap(), &NodeRegistry::new())
+    }
+
+    #[test]
+    fn loading_clamps_the_tempo() {
+        let project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let loaded = reload_edited(&project, |saved| saved.tempo_events[0].bpm = 0.0).unwrap();
+
+        assert_eq!(loaded.tempo_map.events[0].bpm, MIN_BPM);
+        assert_eq!(loaded.tempo_map.beats_to_samples(Beats(1.0)), 48000 * 60);
+    }
+
+    #[test]
+    fn loading_rejects_routing_cycles() {
+        let mut project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let first = project.add_track(bus_with_lookahead(0.0));
+        let second = project.add_track(bus_with_lookahead(0.0));
+        project.add_send(first, second, 1.0).unwrap();
+        assert!(reload_edited(&project, |_| {}).is_ok());
+
+        let result = reload_edited(&project, |saved| {
+            saved.output_buses.insert(second, first);
+        });
+        assert!(matches!(
+            result,
+            Err(ProjectFileError::InvalidRouting(
+                ProjectError::RoutingCycle(..)
+            ))
+        ));
+    }
+
+    #[test]
+    fn tracks_are_processed_before_their_buses() {
+        let mut project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let group = project.add_track(bus_with_lookahead(0.0));
+        let reverb = project.add_track(bus_with_lookahead(0.0));
+        let source = project.add_track(bus_with_lookahead(0.0));
+        project.set_output_bus(reverb, Some(group)).unwrap();
+        project.add_send(source, reverb, 0.5).unwrap();
+        project.prepare().unwrap();
+
+        assert_eq!(project.process_order, [source, reverb, group]);
+    }
+
+    #[test]
+    fn routing_cycles_are_rejected() {
+        let mut project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let first = project.add_track(bus_with_lookahead(0.0));
+        let second = project.add_track(bus_with_lookahead(0.0));
+        let third = project.add_track(bus_with_lookahead(0.0));
+        project.add_send(first, second, 1.0).unwrap();
+        project.set_output_bus(second, Some(third)).unwrap();
+
+        assert!(matches!(
+            project.add_send(third, first, 1.0),
+            Err(ProjectError::RoutingCycle(..))
+        ));
+        assert!(matches!(
+            project.set_output_bus(third, Some(first)),
+            Err(ProjectError::RoutingCycle(..))
+        ));
+        assert!(matches!(
+            project.add_send(first, first, 1.0),
+            Err(ProjectError::RoutingCycle(..))
+        ));
+    }
+
+    #[test]
+    fn tracks_without_latency_are_not_delayed() {
+        let mut project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let first = project.add_track(bus_with_lookahead(0.0));
+        let second = project.add_track(bus_with_lookahead(0.0));
+        project.prepare().unwrap();
+
+        assert_eq!(project.get_track_delay(&first), Some(0));
+        assert_eq!(project.get_track_delay(&second), Some(0));
+    }
+
+    #[test]
+    fn playback_plays_before_the_range_start() {
+        let mut project = Project::new(audio_ctx(), 120.0, Beats(0.0), Beats(4.0));
+        let mut track = AudioTrack::new(audio_ctx());
+        track.add_region(AudioRegion::from_planar(
+            &[vec![0.5; 96000], vec![-0.5; 96000]],
+            48000,
+            120.0,
+            Beats(0.0),
+        ));
+        let id = project.add_track(Box::new(track));
+        project.set_range(Beats(2.0), Beats(4.0));
+        let mut output = vec![0.0; 64 * 2];
+
+        // The playback renders the tracks from the beginning of the timeline
+        project.prepare().unwrap();
+        let track = project.tracks.get_mut(&id).unwrap();
+        track.process(true, 24000, &mut output).unwrap();
+        assert_eq!(&output[..2], [0.5, -0.5]);
+
+        // The export only renders the range
+        project.prepare_for_export().unwrap();
+        let track = project.tracks.get_mut(&id).unwrap();
+        output.fill(0.0);
+        track.process(true, 24000, &mut output).unwrap();
+        assert!(output.iter().all(|sample| *sample == 0.0));
+    }
 }
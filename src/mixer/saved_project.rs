@@ -0,0 +1,29 @@
+use crate::{
+    data_types::{AudioContext, Beats, BitDepth},
+    mixer::{PlaybackEndAction, SummingMode, TempoEvent, TrackID, TrackSend},
+    track::SavedTrack,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The version of the project file format, increased when a saved field changes incompatibly.
+pub(super) const PROJECT_FILE_VERSION: u32 = 1;
+
+/// The contents of a project file.
+#[derive(Serialize, Deserialize)]
+pub(super) struct SavedProject {
+    pub version: u32,
+    pub audio_ctx: AudioContext,
+    pub tempo_events: Vec<TempoEvent>,
+    pub tracks: Vec<(TrackID, SavedTrack)>,
+    pub track_order: Vec<TrackID>,
+    pub sends: HashMap<TrackID, Vec<TrackSend>>,
+    pub output_buses: HashMap<TrackID, TrackID>,
+    pub summing_mode: SummingMode,
+    pub range_start: Beats,
+    pub range_duration: Beats,
+    pub playback_end_action: PlaybackEndAction,
+    pub export_bit_depth: BitDepth,
+    pub export_dither: bool,
+    pub next_track_id: usize,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Determines how the mixer sums the tracks to the output.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummingMode {
+    /// Sums the tracks without scaling.
+    #[default]
+    Sum,
+    /// Divides the sum by the number of the tracks producing audio.
+    Average,
+    /// Scales the sum by 1/sqrt of the number of the tracks producing audio.
+    AutoGain,
+}
+
+impl SummingMode {
+    /// Returns the gain applied to the sum of the given number of active tracks.
+    pub fn get_gain(&self, active_tracks: usize) -> f32 {
+        let active_tracks = active_tracks.max(1) as f32;
+        match self {
+            SummingMode::Sum => 1.0,
+            SummingMode::Average => 1.0 / active_tracks,
+            SummingMode::AutoGain => 1.0 / active_tracks.sqrt(),
+        }
+    }
+}
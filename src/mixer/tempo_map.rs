@@ -3,6 +3,10 @@ use crate::{
     mixer::TempoEvent,
 };
 
+/// The lowest BPM allowed in the tempo map. Lower tempos are clamped to it,
+/// as a tempo of zero makes every beat infinitely long.
+pub const MIN_BPM: f64 = 1.0;
+
 #[derive(Clone, Default)]
 pub struct TempoMap {
     pub events: Vec<TempoEvent>,
@@ -12,12 +16,12 @@ pub struct TempoMap {
 impl TempoMap {
     // --- NEW ---
 
-    /// Creates a new TempoMap.
+    /// Creates a new TempoMap. The initial BPM is clamped to `MIN_BPM`.
     pub fn new(audio_ctx: AudioContext, initial_bpm: f64) -> Self {
         Self {
             events: vec![TempoEvent {
                 beat: Beats(0.0),
-                bpm: initial_bpm,
+                bpm: initial_bpm.max(MIN_BPM),
                 sample_offset: 0,
             }],
             audio_ctx,
@@ -35,8 +39,10 @@ impl TempoMap {
 
     // --- TEMPO EVENT MANAGEMENT ---
 
-    /// Adds a new tempo event to the tempo map.
-    pub fn add_event(&mut self, event: TempoEvent) {
+    /// Adds a new tempo event to the tempo map. The BPM of the event is clamped to `MIN_BPM`.
+    pub fn add_event(&mut self, mut event: TempoEvent) {
+        event.bpm = event.bpm.max(MIN_BPM);
+
         // Insert the event while preserving the order
         let index = match self.events.binary_search(&event) {
             Ok(index) => {
@@ -71,7 +77,7 @@ impl TempoMap {
         }
     }
 
-    /// Change the BPM of the event in the specified index.
+    /// Change the BPM of the event in the specified index. The BPM is clamped to `MIN_BPM`.
     pub fn change_bpm(&mut self, index: usize, bpm: f64) {
         // Get a mutable reference to the target event
         let Some(event) = self.events.get_mut(index) else {
@@ -79,7 +85,7 @@ impl TempoMap {
         };
 
         // Update the bpm
-        event.bpm = bpm;
+        event.bpm = bpm.max(MIN_BPM);
 
         // Calculate the sample offsets of the events after the event
         self.calculate_sample_offsets(index);
@@ -96,7 +102,7 @@ impl TempoMap {
                 let prev = &self.events[i - 1];
                 let beat_diff = self.events[i].beat - prev.beat;
                 let samples =
-                    (beat_diff.0 / prev.bpm * 60.0 * self.audio_ctx.sample_rate as f64) as usize;
+                    (beat_diff.to_seconds(prev.bpm) * self.audio_ctx.sample_rate as f64) as usize;
                 self.events[i].sample_offset = prev.sample_offset + samples;
             }
         }
@@ -110,7 +116,7 @@ impl TempoMap {
         let event = &self.events[idx];
         let remaining_beats = beats - event.beat;
         event.sample_offset
-            + (remaining_beats.0 / event.bpm * 60.0 * self.audio_ctx.sample_rate as f64) as usize
+            + (remaining_beats.to_seconds(event.bpm) * self.audio_ctx.sample_rate as f64) as usize
     }
 
     /// Convert samples to the Beats using the tempo map.
@@ -122,13 +128,47 @@ impl TempoMap {
             .saturating_sub(1);
         let event = &self.events[idx];
 
-        // Calculate the elapsed samples from the event's beats
-        let elapsed_samples = (samples - event.sample_offset) as f64;
+        // Calculate the elapsed seconds from the event's beats
+        let elapsed_seconds =
+            (samples - event.sample_offset) as f64 / self.audio_ctx.sample_rate as f64;
 
-        // Convert the elapsed samples to beats
-        let elapsed_beats =
-            (elapsed_samples * event.bpm) / (60.0 * self.audio_ctx.sample_rate as f64);
+        // Convert the elapsed seconds to beats
+        event.beat + Beats::from_seconds(elapsed_seconds, event.bpm)
+    }
 
-        event.beat + Beats(elapsed_beats)
+    // --- SECONDS CONVERSION ---
+
+    /// Convert the Beats to seconds using the tempo map.
+    pub fn beats_to_seconds(&self, beats: Beats) -> f64 {
+        let mut seconds = 0.0;
+        for (i, event) in self.events.iter().enumerate() {
+            // Get the end of the section, which ends at the next event or at the given beats
+            let section_end = self
+                .events
+                .get(i + 1)
+                .map_or(beats, |next| next.beat.min(beats));
+            if section_end <= event.beat {
+                break;
+            }
+            seconds += (section_end - event.beat).to_seconds(event.bpm);
+        }
+        seconds
+    }
+
+    /// Convert seconds to the Beats using the tempo map.
+    pub fn seconds_to_beats(&self, seconds: f64) -> Beats {
+        let mut remaining_seconds = seconds;
+        for (i, event) in self.events.iter().enumerate() {
+            // Return the beats if the remaining seconds end inside the section
+            let Some(next) = self.events.get(i + 1) else {
+                return event.beat + Beats::from_seconds(remaining_seconds, event.bpm);
+            };
+            let section_seconds = (next.beat - event.beat).to_seconds(event.bpm);
+            if remaining_seconds < section_seconds {
+                return event.beat + Beats::from_seconds(remaining_seconds, event.bpm);
+            }
+            remaining_seconds -= section_seconds;
+        }
+        Beats(0.0)
     }
 }
@@ -1,2 +1,6 @@
-#[derive(Clone, Copy, Default, Eq, Hash, PartialEq, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone, Copy, Default, Eq, Hash, PartialEq, PartialOrd, Ord, Debug, Serialize, Deserialize,
+)]
 pub struct TrackID(pub usize);
@@ -0,0 +1,25 @@
+use crate::{graph::node_id::NodeID, mixer::TrackID};
+use serde::{Deserialize, Serialize};
+
+/// Routes a copy of the track output to a bus track.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrackSend {
+    /// The bus track which receives the audio.
+    pub target: TrackID,
+    /// The gain applied to the sent audio.
+    pub level: f32,
+    /// The tapped node output in the graph of the source track to send instead of the track output,
+    /// as the node ID and the output index. The tap must be registered with `Graph::add_tap`.
+    #[serde(default)]
+    pub tap: Option<(NodeID, usize)>,
+}
+
+impl TrackSend {
+    pub fn new(target: TrackID, level: f32) -> Self {
+        Self {
+            target,
+            level,
+            tap: None,
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::Node,
+};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::{
+    f32::consts::PI,
+    sync::{Arc, Mutex},
+};
+
+/// The level in dBFS which the bins without any energy are clamped to.
+const SPECTRUM_FLOOR_DB: f32 = -120.0;
+
+/// A node which passes the audio through unchanged and analyzes the spectrum of every chunk.
+/// The channels are averaged, windowed with a Hann window and zero-padded to the next power of two of the buffer size.
+/// The spectrum has `fft_size / 2 + 1` bins from 0 Hz to the Nyquist frequency, in dBFS, where a full scale sine is 0 dB.
+/// The spectrum is shared between the clones of the node, so the host can read the spectrum of the node running on the audio thread.
+#[derive(Default, Clone)]
+pub struct AnalyzerNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+
+    // --- ANALYSIS STATE ---
+    fft_size: usize,
+    fft: Option<Arc<dyn Fft<f32>>>,
+    window: Vec<f32>,
+    /// The gain which scales the magnitude of a full scale sine to 1.0.
+    normalization: f32,
+    fft_buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// The spectrum of the last chunk, before it is published.
+    magnitudes: Vec<f32>,
+
+    // --- OUTPUT ---
+    /// The published spectrum, shared between the clones.
+    spectrum: Arc<Mutex<Vec<f32>>>,
+}
+
+impl AnalyzerNode {
+    /// Returns the spectrum of the latest analyzed chunk in dBFS, or an empty vector before the node is prepared.
+    pub fn get_latest_spectrum(&self) -> Vec<f32> {
+        self.spectrum
+            .lock()
+            .map_or(Vec::new(), |spectrum| spectrum.clone())
+    }
+
+    /// Returns the FFT size, which is the buffer size rounded up to a power of two.
+    pub fn get_fft_size(&self) -> usize {
+        self.fft_size
+    }
+}
+
+impl Node for AnalyzerNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        1
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        self.fft_size = self.buffer_size.max(1).next_power_of_two();
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(self.fft_size);
+
+        // Hann window over the chunk, before the zero padding
+        let length = self.buffer_size.max(1);
+        self.window = (0..length)
+            .map(|i| {
+                if length == 1 {
+                    1.0
+                } else {
+                    0.5 - 0.5 * (2.0 * PI * i as f32 / (length - 1) as f32).cos()
+                }
+            })
+            .collect();
+        let window_sum: f32 = self.window.iter().sum();
+        self.normalization = if window_sum > 0.0 {
+            2.0 / window_sum
+        } else {
+            1.0
+        };
+
+        let bins = self.fft_size / 2 + 1;
+        self.fft_buffer = vec![Complex::default(); self.fft_size];
+        self.scratch = vec![Complex::default(); fft.get_inplace_scratch_len()];
+        self.magnitudes = vec![SPECTRUM_FLOOR_DB; bins];
+        self.fft = Some(fft);
+        if let Ok(mut spectrum) = self.spectrum.lock() {
+            *spectrum = self.magnitudes.clone();
+        }
+
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(output)) = (inputs.first(), outputs.first()) else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        let (src, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+        dst.copy_from_slice(src);
+
+        let Some(fft) = &self.fft else {
+            return;
+        };
+        if self.channels == 0 {
+            return;
+        }
+
+        // Average the channels and window the chunk
+        self.fft_buffer.fill(Complex::default());
+        for ((bin, frame), gain) in self
+            .fft_buffer
+            .iter_mut()
+            .zip(src.chunks_exact(self.channels))
+            .zip(self.window.iter())
+        {
+            bin.re = frame.iter().sum::<f32>() / self.channels as f32 * gain;
+        }
+        fft.process_with_scratch(&mut self.fft_buffer, &mut self.scratch);
+
+        // Convert the bins to dBFS
+        for (magnitude, bin) in self.magnitudes.iter_mut().zip(self.fft_buffer.iter()) {
+            let amplitude = bin.norm() * self.normalization;
+            *magnitude = (20.0 * amplitude.log10()).max(SPECTRUM_FLOOR_DB);
+        }
+
+        // Publish the spectrum without waiting, skipping the chunk if the host is reading it
+        if let Ok(mut spectrum) = self.spectrum.try_lock()
+            && spectrum.len() == self.magnitudes.len()
+        {
+            spectrum.copy_from_slice(&self.magnitudes);
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+
+/// The operation which the arithmetic node applies to the inputs.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArithmeticMode {
+    #[default]
+    Add,
+    Subtract,
+    Multiply,
+    Max,
+}
+
+/// A node which combines the "a" and "b" inputs sample by sample.
+/// Useful for blending parallel chains, or ring and amplitude modulation with multiply.
+#[derive(Default, Clone)]
+pub struct ArithmeticNode {
+    data_type: TypeInfo,
+    mode: ArithmeticMode,
+}
+
+impl ArithmeticNode {
+    /// Sets the operation applied to the inputs.
+    pub fn set_mode(&mut self, mode: ArithmeticMode) {
+        self.mode = mode;
+    }
+
+    /// Returns the operation applied to the inputs.
+    pub fn get_mode(&self) -> ArithmeticMode {
+        self.mode
+    }
+}
+
+impl Node for ArithmeticNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["a".to_string(), "b".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        2
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index < 2 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(a), Some(b), Some(output)) = (inputs.first(), inputs.get(1), outputs.first())
+        else {
+            return;
+        };
+        let len = self.data_type.size / 4;
+        let (a, b, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*a as *const f32, len),
+                std::slice::from_raw_parts(*b as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+
+        let op: fn(f32, f32) -> f32 = match self.mode {
+            ArithmeticMode::Add => |a, b| a + b,
+            ArithmeticMode::Subtract => |a, b| a - b,
+            ArithmeticMode::Multiply => |a, b| a * b,
+            ArithmeticMode::Max => f32::max,
+        };
+        for ((d, a), b) in dst.iter_mut().zip(a.iter()).zip(b.iter()) {
+            *d = op(*a, *b);
+        }
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.mode).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.mode = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
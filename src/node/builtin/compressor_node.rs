@@ -0,0 +1,288 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+
+/// The parameters of the compressor, saved in the presets.
+#[derive(Clone, Serialize, Deserialize)]
+struct CompressorParameters {
+    threshold_db: f32,
+    ratio: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    makeup_db: f32,
+    sidechain_enabled: bool,
+    #[serde(default)]
+    lookahead_ms: f32,
+}
+
+impl Default for CompressorParameters {
+    fn default() -> Self {
+        Self {
+            threshold_db: -18.0,
+            ratio: 4.0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+            makeup_db: 0.0,
+            sidechain_enabled: false,
+            lookahead_ms: 0.0,
+        }
+    }
+}
+
+/// A feed-forward compressor node.
+/// The envelope follower keys off the "sidechain" input when the sidechain is enabled, otherwise off the "audio" input.
+/// The detection signal is summed to mono, so the sidechain may carry any signal with the same audio type.
+/// With a lookahead, the audio is delayed behind the detection, so the gain is reduced before the peaks arrive.
+#[derive(Default, Clone)]
+pub struct CompressorNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+    sample_rate: usize,
+
+    // --- PARAMETERS ---
+    params: CompressorParameters,
+
+    // --- STATE ---
+    envelope: f32,
+    /// The ring buffer delaying the audio by the lookahead, allocated in `prepare`.
+    lookahead: Vec<f32>,
+    /// The position of the oldest frame in the lookahead buffer, in samples.
+    lookahead_position: usize,
+}
+
+impl CompressorNode {
+    /// Sets the level in decibels above which the audio is compressed.
+    pub fn set_threshold(&mut self, threshold_db: f32) {
+        self.params.threshold_db = threshold_db;
+    }
+
+    /// Sets the compression ratio. Values below 1 are clamped to 1.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.params.ratio = ratio.max(1.0);
+    }
+
+    /// Sets the attack time in milliseconds.
+    pub fn set_attack(&mut self, attack_ms: f32) {
+        self.params.attack_ms = attack_ms.max(0.0);
+    }
+
+    /// Sets the release time in milliseconds.
+    pub fn set_release(&mut self, release_ms: f32) {
+        self.params.release_ms = release_ms.max(0.0);
+    }
+
+    /// Sets the gain in decibels applied after the compression.
+    pub fn set_makeup_gain(&mut self, makeup_db: f32) {
+        self.params.makeup_db = makeup_db;
+    }
+
+    /// Sets whether the envelope follower keys off the "sidechain" input.
+    /// Unconnected inputs can't be told from silence, so enable this only when the sidechain is connected.
+    pub fn set_sidechain_enabled(&mut self, enabled: bool) {
+        self.params.sidechain_enabled = enabled;
+    }
+
+    /// Returns whether the envelope follower keys off the "sidechain" input.
+    pub fn is_sidechain_enabled(&self) -> bool {
+        self.params.sidechain_enabled
+    }
+
+    /// Sets how long the audio is delayed behind the detection in milliseconds, which is reported as the latency.
+    /// The graph must be prepared again after changing the lookahead.
+    pub fn set_lookahead(&mut self, lookahead_ms: f32) {
+        self.params.lookahead_ms = lookahead_ms.max(0.0);
+    }
+
+    /// Returns the lookahead in samples.
+    fn lookahead_samples(&self) -> usize {
+        (self.params.lookahead_ms * 0.001 * self.sample_rate as f32).round() as usize
+    }
+
+    /// Returns the one-pole smoothing coefficient for the given time in milliseconds.
+    fn time_coefficient(&self, time_ms: f32) -> f32 {
+        let samples = time_ms * 0.001 * self.sample_rate as f32;
+        if samples <= 0.0 {
+            0.0
+        } else {
+            (-1.0 / samples).exp()
+        }
+    }
+}
+
+impl Node for CompressorNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["audio".to_string(), "sidechain".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        2
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index < 2 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+        self.sample_rate = audio_ctx.sample_rate;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        self.envelope = 0.0;
+        self.lookahead = vec![0.0; self.lookahead_samples() * self.channels];
+        self.lookahead_position = 0;
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(output)) = (inputs.first(), outputs.first()) else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        let (src, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+        // Fall back to the main input if the sidechain is disabled
+        let detector = match inputs.get(1) {
+            Some(sidechain) if self.params.sidechain_enabled => unsafe {
+                std::slice::from_raw_parts(*sidechain as *const f32, len)
+            },
+            _ => src,
+        };
+
+        let attack = self.time_coefficient(self.params.attack_ms);
+        let release = self.time_coefficient(self.params.release_ms);
+        let slope = 1.0 - 1.0 / self.params.ratio;
+        let channels = self.channels.max(1);
+
+        for ((src_frame, dst_frame), detector_frame) in src
+            .chunks_exact(channels)
+            .zip(dst.chunks_exact_mut(channels))
+            .zip(detector.chunks_exact(channels))
+        {
+            // Sum the detection signal to mono
+            let level = (detector_frame.iter().sum::<f32>() / channels as f32).abs();
+
+            // Follow the envelope of the detection signal
+            let coefficient = if level > self.envelope {
+                attack
+            } else {
+                release
+            };
+            self.envelope = coefficient * self.envelope + (1.0 - coefficient) * level;
+
+            // Calculate the gain reduction above the threshold
+            let envelope_db = 20.0 * self.envelope.max(1e-9).log10();
+            let over_db = (envelope_db - self.params.threshold_db).max(0.0);
+            let gain = 10f32.powf((self.params.makeup_db - over_db * slope) / 20.0);
+
+            if self.lookahead.is_empty() {
+                for (d, s) in dst_frame.iter_mut().zip(src_frame.iter()) {
+                    *d = *s * gain;
+                }
+                continue;
+            }
+
+            // Output the delayed frame, and store the current frame in its place
+            let position = self.lookahead_position;
+            let delayed = &mut self.lookahead[position..position + channels];
+            for ((d, s), delayed) in dst_frame.iter_mut().zip(src_frame.iter()).zip(delayed) {
+                *d = *delayed * gain;
+                *delayed = *s;
+            }
+            self.lookahead_position = (position + channels) % self.lookahead.len();
+        }
+    }
+
+    fn get_latency(&self) -> usize {
+        self.lookahead_samples()
+    }
+
+    fn is_time_variant(&self) -> bool {
+        true
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.params).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.params = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookahead_delays_the_audio_by_the_latency() {
+        let audio_ctx = AudioContext {
+            channels: 2,
+            sample_rate: 1000,
+            buffer_size: 4,
+            max_voices: 1,
+        };
+        let mut compressor = CompressorNode::default();
+        compressor.set_threshold(0.0);
+        compressor.set_lookahead(3.0);
+        compressor.update(&audio_ctx);
+        compressor.prepare().unwrap();
+        assert_eq!(compressor.get_latency(), 3);
+
+        // An impulse in the first frame leaves the compressor three frames later
+        let mut input = [0.0f32; 8];
+        input[0] = 0.5;
+        input[1] = -0.5;
+        let mut output = [1.0f32; 8];
+        compressor.process(
+            &[input.as_ptr() as *const u8],
+            &[output.as_mut_ptr() as *mut u8],
+            &audio_ctx,
+        );
+        assert_eq!(output, [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5, -0.5]);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::sync::Arc;
+
+/// A node that convolves the audio with an impulse response.
+/// Uses uniformly partitioned overlap-add convolution, so long impulse responses stay cheap.
+/// The partitions are as long as the buffer, and each block is output in the chunk it is received, so the node adds no latency.
+#[derive(Default, Clone)]
+pub struct ConvolutionNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+
+    // --- IMPULSE RESPONSE ---
+    /// Interleaved impulse response samples.
+    impulse: Vec<f32>,
+    impulse_channels: usize,
+
+    // --- CONVOLUTION STATE ---
+    fft_size: usize,
+    forward_fft: Option<Arc<dyn Fft<f32>>>,
+    inverse_fft: Option<Arc<dyn Fft<f32>>>,
+    /// Spectra of the impulse partitions for each impulse channel.
+    partitions: Vec<Vec<Vec<Complex<f32>>>>,
+    /// Spectra of the previous input blocks for each channel, used as a ring buffer.
+    delay_line: Vec<Vec<Vec<Complex<f32>>>>,
+    delay_line_pos: usize,
+    /// The tail of the previous block for each channel, added to the next block.
+    overlap: Vec<Vec<f32>>,
+    fft_buffer: Vec<Complex<f32>>,
+    accumulator: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl ConvolutionNode {
+    /// Sets the impulse response from the interleaved samples.
+    /// The impulse must have the same sample rate as the audio context. A mono impulse is applied to every channel.
+    /// The node must be prepared again after setting the impulse.
+    pub fn set_impulse(&mut self, impulse: Vec<f32>, channels: usize) {
+        self.impulse = impulse;
+        self.impulse_channels = channels.max(1);
+    }
+
+    /// Returns the number of frames of the impulse response.
+    pub fn get_impulse_frames(&self) -> usize {
+        self.impulse.len() / self.impulse_channels.max(1)
+    }
+}
+
+impl Node for ConvolutionNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        1
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        // Each block is zero-padded to twice the buffer size, so the linear convolution of a block
+        // and an impulse partition of the same length fits without wrapping around
+        self.fft_size = self.buffer_size * 2;
+        let mut planner = FftPlanner::<f32>::new();
+        let forward_fft = planner.plan_fft_forward(self.fft_size);
+        let inverse_fft = planner.plan_fft_inverse(self.fft_size);
+        let scratch_len = forward_fft
+            .get_inplace_scratch_len()
+            .max(inverse_fft.get_inplace_scratch_len());
+
+        // Split the impulse into partitions of the buffer size and transform them
+        let impulse_frames = self.get_impulse_frames();
+        let partition_count = impulse_frames.div_ceil(self.buffer_size.max(1));
+        self.partitions = (0..self.impulse_channels)
+            .map(|channel| {
+                (0..partition_count)
+                    .map(|partition| {
+                        let mut spectrum = vec![Complex::default(); self.fft_size];
+                        let start = partition * self.buffer_size;
+                        let end = (start + self.buffer_size).min(impulse_frames);
+                        for (frame, bin) in (start..end).zip(spectrum.iter_mut()) {
+                            bin.re = self.impulse[frame * self.impulse_channels + channel];
+                        }
+                        forward_fft.process(&mut spectrum);
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Reset the convolution state
+        self.delay_line =
+            vec![vec![vec![Complex::default(); self.fft_size]; partition_count]; self.channels];
+        self.delay_line_pos = 0;
+        self.overlap = vec![vec![0.0; self.buffer_size]; self.channels];
+        self.fft_buffer = vec![Complex::default(); self.fft_size];
+        self.accumulator = vec![Complex::default(); self.fft_size];
+        self.scratch = vec![Complex::default(); scratch_len];
+        self.forward_fft = Some(forward_fft);
+        self.inverse_fft = Some(inverse_fft);
+
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(output)) = (inputs.first(), outputs.first()) else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        let (src, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+
+        // Output silence if the node has no impulse or has not been prepared
+        let (Some(forward_fft), Some(inverse_fft)) = (&self.forward_fft, &self.inverse_fft) else {
+            dst.fill(0.0);
+            return;
+        };
+        let partition_count = self.partitions.first().map_or(0, |p| p.len());
+        if partition_count == 0 {
+            dst.fill(0.0);
+            return;
+        }
+
+        let scale = 1.0 / self.fft_size as f32;
+        for channel in 0..self.channels {
+            // Transform the zero-padded input block of the channel
+            self.fft_buffer.fill(Complex::default());
+            for (bin, sample) in self
+                .fft_buffer
+                .iter_mut()
+                .zip(src.iter().skip(channel).step_by(self.channels))
+            {
+                bin.re = *sample;
+            }
+            forward_fft.process_with_scratch(&mut self.fft_buffer, &mut self.scratch);
+
+            // Store the spectrum in the delay line
+            let delay_line = &mut self.delay_line[channel];
+            delay_line[self.delay_line_pos].copy_from_slice(&self.fft_buffer);
+
+            // Multiply the delayed input spectra with the impulse partitions and accumulate them
+            let partitions = &self.partitions[channel % self.impulse_channels];
+            self.accumulator.fill(Complex::default());
+            for (index, partition) in partitions.iter().enumerate() {
+                let delayed =
+                    &delay_line[(self.delay_line_pos + partition_count - index) % partition_count];
+                for ((acc, x), h) in self
+                    .accumulator
+                    .iter_mut()
+                    .zip(delayed.iter())
+                    .zip(partition.iter())
+                {
+                    *acc += x * h;
+                }
+            }
+            inverse_fft.process_with_scratch(&mut self.accumulator, &mut self.scratch);
+
+            // Write the first half with the previous tail, and keep the second half as the next tail
+            let overlap = &mut self.overlap[channel];
+            for (frame, sample) in dst
+                .iter_mut()
+                .skip(channel)
+                .step_by(self.channels)
+                .enumerate()
+            {
+                *sample = self.accumulator[frame].re * scale + overlap[frame];
+            }
+            for (tail, bin) in overlap
+                .iter_mut()
+                .zip(self.accumulator[self.buffer_size..].iter())
+            {
+                *tail = bin.re * scale;
+            }
+        }
+
+        // Advance the delay line
+        self.delay_line_pos = (self.delay_line_pos + 1) % partition_count;
+    }
+
+    fn is_time_variant(&self) -> bool {
+        true
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&(&self.impulse, self.impulse_channels)).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        let (impulse, channels): (Vec<f32>, usize) = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        self.set_impulse(impulse, channels);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
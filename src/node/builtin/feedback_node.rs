@@ -0,0 +1,133 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::Node,
+};
+
+/// The sending end of a feedback loop, which passes its input to the paired `FeedbackReturnNode`.
+/// Pair the nodes with `Graph::add_feedback`. The graph copies the input after processing every chunk,
+/// so the return node outputs it in the next chunk, which adds one chunk of latency to the feedback path.
+#[derive(Default, Clone)]
+pub struct FeedbackSendNode {
+    data_type: TypeInfo,
+}
+
+impl Node for FeedbackSendNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn get_input_len(&self) -> usize {
+        1
+    }
+
+    fn get_output_len(&self) -> usize {
+        0
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, _index: usize) -> Option<&TypeInfo> {
+        None
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        Ok(())
+    }
+
+    fn process(&mut self, _inputs: &[*const u8], _outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        // The graph copies the input to the paired return node
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// The returning end of a feedback loop, which outputs the input of the paired `FeedbackSendNode`
+/// from the previous chunk. Outputs silence for the first chunk after the graph is prepared.
+#[derive(Default, Clone)]
+pub struct FeedbackReturnNode {
+    data_type: TypeInfo,
+}
+
+impl Node for FeedbackReturnNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        0
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, _index: usize) -> Option<&TypeInfo> {
+        None
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        Ok(())
+    }
+
+    fn process(&mut self, _inputs: &[*const u8], _outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        // The output buffer already holds the chunk copied by the graph
+    }
+
+    // The output is carried over from the previous chunk
+    fn is_time_variant(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
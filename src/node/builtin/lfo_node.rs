@@ -0,0 +1,204 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// The waveform of the LFO.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+    /// Holds a random value for each cycle.
+    Random,
+}
+
+/// The parameters of the LFO, saved in the presets.
+#[derive(Clone, Serialize, Deserialize)]
+struct LfoParameters {
+    shape: LfoShape,
+    rate_hz: f32,
+    depth: f32,
+    /// The phase offset in cycles between 0 and 1.
+    phase: f32,
+}
+
+impl Default for LfoParameters {
+    fn default() -> Self {
+        Self {
+            shape: LfoShape::Sine,
+            rate_hz: 1.0,
+            depth: 1.0,
+            phase: 0.0,
+        }
+    }
+}
+
+/// A low frequency oscillator which outputs a modulation signal between -depth and depth.
+/// The signal has the audio type with the same value in every channel, so it can be connected to any audio input.
+#[derive(Default, Clone)]
+pub struct LfoNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+    sample_rate: usize,
+
+    // --- PARAMETERS ---
+    params: LfoParameters,
+
+    // --- STATE ---
+    /// The current position in the cycle between 0 and 1.
+    position: f32,
+    random_value: f32,
+    seed: u32,
+}
+
+impl LfoNode {
+    /// Sets the waveform of the LFO.
+    pub fn set_shape(&mut self, shape: LfoShape) {
+        self.params.shape = shape;
+    }
+
+    /// Sets the rate of the LFO in Hz.
+    pub fn set_rate(&mut self, rate_hz: f32) {
+        self.params.rate_hz = rate_hz.max(0.0);
+    }
+
+    /// Sets the rate of the LFO so that a cycle lasts the given beats at the given tempo.
+    /// The rate must be set again when the tempo changes.
+    pub fn set_rate_synced(&mut self, beats_per_cycle: f64, bpm: f64) {
+        if beats_per_cycle > 0.0 {
+            self.set_rate((bpm / 60.0 / beats_per_cycle) as f32);
+        }
+    }
+
+    /// Sets the amplitude of the modulation signal.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.params.depth = depth;
+    }
+
+    /// Sets the phase offset in cycles.
+    pub fn set_phase(&mut self, phase: f32) {
+        self.params.phase = phase.rem_euclid(1.0);
+    }
+
+    /// Returns the next random value between -1 and 1.
+    fn next_random(&mut self) -> f32 {
+        // Xorshift32
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// Returns the value of the waveform at the phase between 0 and 1.
+    fn evaluate(&self, phase: f32) -> f32 {
+        match self.params.shape {
+            LfoShape::Sine => (phase * TAU).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * ((phase + 0.25).rem_euclid(1.0) - 0.5).abs(),
+            LfoShape::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            LfoShape::Random => self.random_value,
+        }
+    }
+}
+
+impl Node for LfoNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["modulation".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        0
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, _index: usize) -> Option<&TypeInfo> {
+        None
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+        self.sample_rate = audio_ctx.sample_rate;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        self.position = 0.0;
+        self.seed = 0x9E37_79B9;
+        self.random_value = self.next_random();
+        Ok(())
+    }
+
+    fn process(&mut self, _inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let Some(output) = outputs.first() else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        let dst = unsafe { std::slice::from_raw_parts_mut(*output as *mut f32, len) };
+
+        let increment = self.params.rate_hz / self.sample_rate.max(1) as f32;
+        for frame in dst.chunks_exact_mut(self.channels.max(1)) {
+            let phase = (self.position + self.params.phase).rem_euclid(1.0);
+            frame.fill(self.evaluate(phase) * self.params.depth);
+
+            // Advance the position and pick a new random value for each cycle
+            self.position += increment;
+            if self.position >= 1.0 {
+                self.position = self.position.rem_euclid(1.0);
+                self.random_value = self.next_random();
+            }
+        }
+    }
+
+    fn is_time_variant(&self) -> bool {
+        true
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.params).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.params = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
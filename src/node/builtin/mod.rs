@@ -1,7 +1,27 @@
+mod analyzer_node;
+mod arithmetic_node;
 mod audio_input_node;
 mod audio_output_node;
+mod compressor_node;
+mod convolution_node;
+mod feedback_node;
+mod lfo_node;
 mod note_input_node;
+mod panner_node;
+mod parametric_eq_node;
+mod reverb_node;
+mod waveshaper_node;
 
+pub use analyzer_node::AnalyzerNode;
+pub use arithmetic_node::{ArithmeticMode, ArithmeticNode};
 pub use audio_input_node::AudioInputNode;
 pub use audio_output_node::AudioOutputNode;
+pub use compressor_node::CompressorNode;
+pub use convolution_node::ConvolutionNode;
+pub use feedback_node::{FeedbackReturnNode, FeedbackSendNode};
+pub use lfo_node::{LfoNode, LfoShape};
 pub use note_input_node::NoteInputNode;
+pub use panner_node::PannerNode;
+pub use parametric_eq_node::{EqBand, EqBandType, ParametricEqNode};
+pub use reverb_node::ReverbNode;
+pub use waveshaper_node::{WaveshaperCurve, WaveshaperNode};
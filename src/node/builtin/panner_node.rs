@@ -0,0 +1,152 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+/// The parameters of the panner, saved in the presets.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct PannerParameters {
+    pan: f32,
+    mono_input: bool,
+}
+
+/// A node which pans the first two channels of the audio with the equal-power law.
+/// The "pan" input is added to the pan parameter per frame, reading the first channel, so an LFO can modulate the pan.
+/// A mono input is positioned between the channels, and a stereo input is balanced by attenuating one side.
+#[derive(Default, Clone)]
+pub struct PannerNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+
+    // --- PARAMETERS ---
+    params: PannerParameters,
+}
+
+impl PannerNode {
+    /// Sets the pan between -1 (left) and 1 (right).
+    pub fn set_pan(&mut self, pan: f32) {
+        self.params.pan = pan.clamp(-1.0, 1.0);
+    }
+
+    /// Returns the pan between -1 (left) and 1 (right).
+    pub fn get_pan(&self) -> f32 {
+        self.params.pan
+    }
+
+    /// Sets whether the input is treated as mono, which sums the first two channels before positioning.
+    pub fn set_mono_input(&mut self, mono_input: bool) {
+        self.params.mono_input = mono_input;
+    }
+}
+
+impl Node for PannerNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["audio".to_string(), "pan".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        2
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index < 2 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(pan), Some(output)) =
+            (inputs.first(), inputs.get(1), outputs.first())
+        else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        let (src, pan, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts(*pan as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+        dst.copy_from_slice(src);
+
+        // Panning needs at least two channels
+        if self.channels < 2 {
+            return;
+        }
+
+        for (frame, modulation) in dst
+            .chunks_exact_mut(self.channels)
+            .zip(pan.iter().step_by(self.channels))
+        {
+            let pan = (self.params.pan + modulation).clamp(-1.0, 1.0);
+            let (left, right) = if self.params.mono_input {
+                // Position the mono signal between the channels
+                let mono = (frame[0] + frame[1]) * 0.5;
+                let angle = (pan + 1.0) * 0.5 * FRAC_PI_2;
+                (mono * angle.cos(), mono * angle.sin())
+            } else {
+                // Attenuate the opposite side to balance the stereo signal
+                let left_gain = (pan.max(0.0) * FRAC_PI_2).cos();
+                let right_gain = ((-pan).max(0.0) * FRAC_PI_2).cos();
+                (frame[0] * left_gain, frame[1] * right_gain)
+            };
+            frame[0] = left;
+            frame[1] = right;
+        }
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.params).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.params = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// The filter shape of an EQ band.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EqBandType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+}
+
+/// A band of the parametric EQ.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EqBand {
+    pub band_type: EqBandType,
+    /// The center or corner frequency in Hz.
+    pub frequency: f32,
+    /// The gain in decibels. Ignored by the pass filters.
+    pub gain_db: f32,
+    pub q: f32,
+}
+
+impl EqBand {
+    pub fn new(band_type: EqBandType, frequency: f32, gain_db: f32, q: f32) -> Self {
+        Self {
+            band_type,
+            frequency,
+            gain_db,
+            q,
+        }
+    }
+
+    /// Calculates the normalized biquad coefficients [b0, b1, b2, a1, a2] of the band.
+    fn coefficients(&self, sample_rate: usize) -> [f32; 5] {
+        let nyquist = sample_rate as f32 * 0.5;
+        let frequency = self.frequency.clamp(1.0, (nyquist - 1.0).max(1.0));
+        let omega = 2.0 * PI * frequency / sample_rate.max(1) as f32;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * self.q.max(0.01));
+        let a = 10f32.powf(self.gain_db / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match self.band_type {
+            EqBandType::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            EqBandType::LowShelf => {
+                let sqrt_a = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a),
+                    (a + 1.0) + (a - 1.0) * cos + sqrt_a,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - sqrt_a,
+                )
+            }
+            EqBandType::HighShelf => {
+                let sqrt_a = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a),
+                    (a + 1.0) - (a - 1.0) * cos + sqrt_a,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - sqrt_a,
+                )
+            }
+            EqBandType::LowPass => (
+                (1.0 - cos) * 0.5,
+                1.0 - cos,
+                (1.0 - cos) * 0.5,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            EqBandType::HighPass => (
+                (1.0 + cos) * 0.5,
+                -(1.0 + cos),
+                (1.0 + cos) * 0.5,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+        };
+
+        [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0]
+    }
+}
+
+/// A parametric EQ node which processes the audio through the biquad bands in series.
+#[derive(Default, Clone)]
+pub struct ParametricEqNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+    sample_rate: usize,
+
+    // --- BANDS ---
+    bands: Vec<EqBand>,
+
+    // --- FILTER STATE ---
+    coefficients: Vec<[f32; 5]>,
+    /// The transposed direct form II state of each band for each channel.
+    states: Vec<Vec<[f32; 2]>>,
+}
+
+impl ParametricEqNode {
+    // --- BAND MANAGEMENT ---
+
+    /// Returns the bands of the EQ in the processing order.
+    pub fn get_bands(&self) -> &[EqBand] {
+        &self.bands
+    }
+
+    /// Adds a band after the existing bands, and returns the index of the band.
+    pub fn add_band(&mut self, band: EqBand) -> usize {
+        self.coefficients.push(band.coefficients(self.sample_rate));
+        self.states.push(vec![[0.0; 2]; self.channels]);
+        self.bands.push(band);
+        self.bands.len() - 1
+    }
+
+    /// Removes the band at the index, and returns the removed band.
+    pub fn remove_band(&mut self, index: usize) -> Option<EqBand> {
+        if index >= self.bands.len() {
+            return None;
+        }
+        self.coefficients.remove(index);
+        self.states.remove(index);
+        Some(self.bands.remove(index))
+    }
+
+    /// Replaces the parameters of the band at the index, keeping the filter state.
+    pub fn set_band(&mut self, index: usize, band: EqBand) {
+        if let (Some(dst), Some(coefficients)) =
+            (self.bands.get_mut(index), self.coefficients.get_mut(index))
+        {
+            *dst = band;
+            *coefficients = band.coefficients(self.sample_rate);
+        }
+    }
+}
+
+impl Node for ParametricEqNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        1
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+        self.sample_rate = audio_ctx.sample_rate;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        // Recalculate the coefficients and reset the filter state
+        self.coefficients = self
+            .bands
+            .iter()
+            .map(|band| band.coefficients(self.sample_rate))
+            .collect();
+        self.states = vec![vec![[0.0; 2]; self.channels]; self.bands.len()];
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(output)) = (inputs.first(), outputs.first()) else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        let (src, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+        dst.copy_from_slice(src);
+
+        let channels = self.channels.max(1);
+        for (coefficients, states) in self.coefficients.iter().zip(self.states.iter_mut()) {
+            let [b0, b1, b2, a1, a2] = *coefficients;
+            for frame in dst.chunks_exact_mut(channels) {
+                for (sample, state) in frame.iter_mut().zip(states.iter_mut()) {
+                    let x = *sample;
+                    let y = b0 * x + state[0];
+                    state[0] = b1 * x - a1 * y + state[1];
+                    state[1] = b2 * x - a2 * y;
+                    *sample = y;
+                }
+            }
+        }
+    }
+
+    fn is_time_variant(&self) -> bool {
+        true
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.bands).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.bands = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
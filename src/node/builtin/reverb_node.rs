@@ -0,0 +1,309 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+
+// --- FREEVERB TUNING ---
+
+/// The lengths of the comb filters of the left channel in samples at 44.1kHz.
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+/// The lengths of the allpass filters of the left channel in samples at 44.1kHz.
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+/// The number of samples the filters of the right channel are longer by, which decorrelates the channels.
+const STEREO_SPREAD: usize = 23;
+const TUNING_SAMPLE_RATE: f32 = 44100.0;
+const FIXED_GAIN: f32 = 0.015;
+const SCALE_WET: f32 = 3.0;
+const SCALE_DRY: f32 = 2.0;
+const SCALE_DAMPING: f32 = 0.4;
+const SCALE_ROOM: f32 = 0.28;
+const OFFSET_ROOM: f32 = 0.7;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// The parameters of the reverb, saved in the presets.
+#[derive(Clone, Serialize, Deserialize)]
+struct ReverbParameters {
+    room_size: f32,
+    damping: f32,
+    wet: f32,
+    dry: f32,
+    width: f32,
+}
+
+impl Default for ReverbParameters {
+    fn default() -> Self {
+        Self {
+            room_size: 0.5,
+            damping: 0.5,
+            wet: 1.0 / SCALE_WET,
+            dry: 0.0,
+            width: 1.0,
+        }
+    }
+}
+
+/// A feedback comb filter with a one-pole lowpass in the feedback path.
+#[derive(Clone, Default)]
+struct CombFilter {
+    buffer: Vec<f32>,
+    position: usize,
+    filter_store: f32,
+}
+
+impl CombFilter {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            position: 0,
+            filter_store: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.position];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.buffer[self.position] = input + self.filter_store * feedback;
+        self.position = (self.position + 1) % self.buffer.len();
+        output
+    }
+}
+
+/// A Schroeder allpass filter, which diffuses the echoes without colouring the spectrum.
+#[derive(Clone, Default)]
+struct AllpassFilter {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl AllpassFilter {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            position: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.position];
+        self.buffer[self.position] = input + delayed * ALLPASS_FEEDBACK;
+        self.position = (self.position + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+/// A stereo reverb node with the Freeverb topology, which runs parallel comb filters into series allpass filters.
+/// The input is summed to mono and reverberated into the first two channels, and the width spreads the reverb between them.
+/// The "room_size", "damping", "wet", "dry" and "width" inputs are added to the parameters per frame, reading the first channel.
+/// With a single channel, only the left reverb is output. Extra channels receive the dry signal.
+#[derive(Default, Clone)]
+pub struct ReverbNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+    sample_rate: usize,
+
+    // --- PARAMETERS ---
+    params: ReverbParameters,
+
+    // --- STATE ---
+    /// The comb filters of the left and right channels.
+    combs: [Vec<CombFilter>; 2],
+    /// The allpass filters of the left and right channels.
+    allpasses: [Vec<AllpassFilter>; 2],
+}
+
+impl ReverbNode {
+    /// Sets the size of the room between 0 and 1, which lengthens the decay.
+    pub fn set_room_size(&mut self, room_size: f32) {
+        self.params.room_size = room_size.clamp(0.0, 1.0);
+    }
+
+    /// Returns the size of the room between 0 and 1.
+    pub fn get_room_size(&self) -> f32 {
+        self.params.room_size
+    }
+
+    /// Sets how much the high frequencies are absorbed between 0 and 1.
+    pub fn set_damping(&mut self, damping: f32) {
+        self.params.damping = damping.clamp(0.0, 1.0);
+    }
+
+    /// Sets the level of the reverberated signal between 0 and 1.
+    pub fn set_wet(&mut self, wet: f32) {
+        self.params.wet = wet.clamp(0.0, 1.0);
+    }
+
+    /// Sets the level of the input signal between 0 and 1. Keep it at 0 on a send bus.
+    pub fn set_dry(&mut self, dry: f32) {
+        self.params.dry = dry.clamp(0.0, 1.0);
+    }
+
+    /// Sets the stereo width of the reverb between 0 (mono) and 1 (fully separated channels).
+    pub fn set_width(&mut self, width: f32) {
+        self.params.width = width.clamp(0.0, 1.0);
+    }
+
+    /// Returns the comb and allpass filters of a channel, scaled to the sample rate.
+    fn create_filters(&self, spread: usize) -> (Vec<CombFilter>, Vec<AllpassFilter>) {
+        let scale = self.sample_rate as f32 / TUNING_SAMPLE_RATE;
+        let length = |tuning: usize| ((tuning + spread) as f32 * scale) as usize;
+        (
+            COMB_TUNINGS
+                .iter()
+                .map(|tuning| CombFilter::new(length(*tuning)))
+                .collect(),
+            ALLPASS_TUNINGS
+                .iter()
+                .map(|tuning| AllpassFilter::new(length(*tuning)))
+                .collect(),
+        )
+    }
+}
+
+impl Node for ReverbNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec![
+            "audio".to_string(),
+            "room_size".to_string(),
+            "damping".to_string(),
+            "wet".to_string(),
+            "dry".to_string(),
+            "width".to_string(),
+        ]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        6
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index < 6 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+        self.sample_rate = audio_ctx.sample_rate;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        // Size the delay lines for the sample rate, which also clears the tail
+        let (left_combs, left_allpasses) = self.create_filters(0);
+        let (right_combs, right_allpasses) = self.create_filters(STEREO_SPREAD);
+        self.combs = [left_combs, right_combs];
+        self.allpasses = [left_allpasses, right_allpasses];
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(output)) = (inputs.first(), outputs.first()) else {
+            return;
+        };
+        let channels = self.channels.max(1);
+        let len = self.channels * self.buffer_size;
+        let (src, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+        // The modulation of each parameter, or None if the input is missing
+        let modulations: [Option<&[f32]>; 5] = std::array::from_fn(|index| {
+            inputs
+                .get(index + 1)
+                .map(|ptr| unsafe { std::slice::from_raw_parts(*ptr as *const f32, len) })
+        });
+        let modulation = |index: usize, frame: usize| {
+            modulations[index].map_or(0.0, |samples| samples[frame * channels])
+        };
+
+        for (frame, (src_frame, dst_frame)) in src
+            .chunks_exact(channels)
+            .zip(dst.chunks_exact_mut(channels))
+            .enumerate()
+        {
+            let room_size = (self.params.room_size + modulation(0, frame)).clamp(0.0, 1.0);
+            let damping = (self.params.damping + modulation(1, frame)).clamp(0.0, 1.0);
+            let wet = (self.params.wet + modulation(2, frame)).clamp(0.0, 1.0) * SCALE_WET;
+            let dry = (self.params.dry + modulation(3, frame)).clamp(0.0, 1.0) * SCALE_DRY;
+            let width = (self.params.width + modulation(4, frame)).clamp(0.0, 1.0);
+
+            let feedback = room_size * SCALE_ROOM + OFFSET_ROOM;
+            let damping = damping * SCALE_DAMPING;
+            let wet_direct = wet * (width / 2.0 + 0.5);
+            let wet_cross = wet * ((1.0 - width) / 2.0);
+
+            // Reverberate the mono sum through the filters of each channel
+            let input = src_frame.iter().take(2).sum::<f32>() * FIXED_GAIN;
+            let [left, right] = [0, 1].map(|side| {
+                let mut sum = self.combs[side]
+                    .iter_mut()
+                    .map(|comb| comb.process(input, feedback, damping))
+                    .sum::<f32>();
+                for allpass in self.allpasses[side].iter_mut() {
+                    sum = allpass.process(sum);
+                }
+                sum
+            });
+
+            if channels == 1 {
+                dst_frame[0] = left * wet + src_frame[0] * dry;
+                continue;
+            }
+            dst_frame[0] = left * wet_direct + right * wet_cross + src_frame[0] * dry;
+            dst_frame[1] = right * wet_direct + left * wet_cross + src_frame[1] * dry;
+            for (d, s) in dst_frame.iter_mut().zip(src_frame.iter()).skip(2) {
+                *d = *s * dry;
+            }
+        }
+    }
+
+    fn is_time_variant(&self) -> bool {
+        true
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.params).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.params = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
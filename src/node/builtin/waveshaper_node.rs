@@ -0,0 +1,241 @@
+use crate::{
+    data_types::{AudioContext, TypeInfo},
+    graph::error::NodeError,
+    node::{Node, error::PresetError},
+};
+use serde::{Deserialize, Serialize};
+
+/// The transfer curve of the waveshaper.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WaveshaperCurve {
+    /// Smooth saturation with the hyperbolic tangent.
+    #[default]
+    Tanh,
+    /// Clips the signal at -1.0 and 1.0.
+    HardClip,
+    /// A cubic curve which saturates smoothly and clips at -1.0 and 1.0.
+    SoftCubic,
+    /// Folds the signal back from -1.0 and 1.0, adding bright harmonics.
+    Foldback,
+}
+
+impl WaveshaperCurve {
+    /// Applies the curve to the sample.
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            WaveshaperCurve::Tanh => x.tanh(),
+            WaveshaperCurve::HardClip => x.clamp(-1.0, 1.0),
+            WaveshaperCurve::SoftCubic => {
+                let x = x.clamp(-1.0, 1.0);
+                1.5 * (x - x * x * x / 3.0)
+            }
+            WaveshaperCurve::Foldback => ((x - 1.0).rem_euclid(4.0) - 2.0).abs() - 1.0,
+        }
+    }
+}
+
+/// The parameters of the waveshaper, saved in the presets.
+#[derive(Clone, Serialize, Deserialize)]
+struct WaveshaperParameters {
+    curve: WaveshaperCurve,
+    drive: f32,
+    oversampling: usize,
+}
+
+impl Default for WaveshaperParameters {
+    fn default() -> Self {
+        Self {
+            curve: WaveshaperCurve::Tanh,
+            drive: 1.0,
+            oversampling: 1,
+        }
+    }
+}
+
+/// A distortion node which scales the audio by the drive and shapes it with the curve.
+/// The "drive" input is added to the drive parameter per frame, reading the first channel, and the output
+/// is compensated so a full scale input stays at full scale regardless of the drive.
+/// Shaping adds harmonics which alias above the Nyquist frequency. The "oversampling" input is added to the
+/// oversampling factor, reading the first sample. Oversampling reduces the aliasing by shaping linearly
+/// interpolated sub-samples and averaging them, and without it the aliasing is left as is.
+#[derive(Default, Clone)]
+pub struct WaveshaperNode {
+    data_type: TypeInfo,
+    channels: usize,
+    buffer_size: usize,
+
+    // --- PARAMETERS ---
+    params: WaveshaperParameters,
+
+    // --- STATE ---
+    /// The last input sample of each channel, used to interpolate the sub-samples.
+    previous: Vec<f32>,
+}
+
+impl WaveshaperNode {
+    /// Sets the transfer curve.
+    pub fn set_curve(&mut self, curve: WaveshaperCurve) {
+        self.params.curve = curve;
+    }
+
+    /// Sets the gain applied to the input before the curve.
+    pub fn set_drive(&mut self, drive: f32) {
+        self.params.drive = drive.max(0.0);
+    }
+
+    /// Sets the oversampling factor, which is 1 for no oversampling, 2 or 4. Other factors are rounded down to them.
+    pub fn set_oversampling(&mut self, factor: usize) {
+        self.params.oversampling = Self::snap_factor(factor);
+    }
+
+    /// Rounds the factor down to 1, 2 or 4.
+    fn snap_factor(factor: usize) -> usize {
+        match factor {
+            0 | 1 => 1,
+            2 | 3 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Returns the gain which brings a full scale input back to full scale after the curve.
+    fn get_compensation(curve: WaveshaperCurve, drive: f32) -> f32 {
+        if curve == WaveshaperCurve::Foldback {
+            return 1.0;
+        }
+        let peak = curve.shape(drive);
+        if peak > 0.0 { 1.0 / peak } else { 1.0 }
+    }
+}
+
+impl Node for WaveshaperNode {
+    fn clone_box(&self) -> Box<dyn Node> {
+        Box::new(self.clone())
+    }
+
+    fn get_input_names(&self) -> Vec<String> {
+        vec![
+            "audio".to_string(),
+            "drive".to_string(),
+            "oversampling".to_string(),
+        ]
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["audio".to_string()]
+    }
+
+    fn get_input_len(&self) -> usize {
+        3
+    }
+
+    fn get_output_len(&self) -> usize {
+        1
+    }
+
+    fn get_input_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index < 3 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn get_output_type(&self, index: usize) -> Option<&TypeInfo> {
+        if index == 0 {
+            Some(&self.data_type)
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, audio_ctx: &AudioContext) {
+        self.data_type = TypeInfo::new(4 * audio_ctx.channels * audio_ctx.buffer_size, 4);
+        self.channels = audio_ctx.channels;
+        self.buffer_size = audio_ctx.buffer_size;
+    }
+
+    fn prepare(&mut self) -> Result<(), Box<dyn NodeError>> {
+        self.previous = vec![0.0; self.channels];
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], _audio_ctx: &AudioContext) {
+        let (Some(input), Some(drive), Some(oversampling), Some(output)) = (
+            inputs.first(),
+            inputs.get(1),
+            inputs.get(2),
+            outputs.first(),
+        ) else {
+            return;
+        };
+        let len = self.channels * self.buffer_size;
+        if len == 0 {
+            return;
+        }
+        let (src, drive, oversampling, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(*input as *const f32, len),
+                std::slice::from_raw_parts(*drive as *const f32, len),
+                std::slice::from_raw_parts(*oversampling as *const f32, len),
+                std::slice::from_raw_parts_mut(*output as *mut f32, len),
+            )
+        };
+        if self.previous.len() != self.channels {
+            self.previous = vec![0.0; self.channels];
+        }
+
+        let curve = self.params.curve;
+        let factor = Self::snap_factor(
+            (self.params.oversampling as f32 + oversampling[0])
+                .round()
+                .max(1.0) as usize,
+        );
+        for ((out_frame, in_frame), modulation) in dst
+            .chunks_exact_mut(self.channels)
+            .zip(src.chunks_exact(self.channels))
+            .zip(drive.iter().step_by(self.channels))
+        {
+            let drive = (self.params.drive + modulation).max(0.0);
+            let compensation = Self::get_compensation(curve, drive);
+            for ((d, s), previous) in out_frame
+                .iter_mut()
+                .zip(in_frame.iter())
+                .zip(self.previous.iter_mut())
+            {
+                let x = *s * drive;
+
+                // Upsample between the previous and the current sample, then shape and average the sub-samples
+                let mut sum = 0.0;
+                for step in 1..=factor {
+                    let t = step as f32 / factor as f32;
+                    sum += curve.shape(*previous + (x - *previous) * t);
+                }
+                *d = sum / factor as f32 * compensation;
+                *previous = x;
+            }
+        }
+    }
+
+    fn is_time_variant(&self) -> bool {
+        // The oversampling interpolates from the previous sample
+        true
+    }
+
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        rmp_serde::to_vec(&self.params).ok()
+    }
+
+    fn import_preset(&mut self, preset: &[u8]) -> Result<(), PresetError> {
+        self.params = rmp_serde::from_slice(preset)
+            .map_err(|err| PresetError::InvalidPreset(err.to_string()))?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum PresetError {
+    /// The node has no parameters to be saved as a preset.
+    Unsupported,
+    /// The preset data could not be decoded for the node.
+    InvalidPreset(String),
+}
+
+impl Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresetError::Unsupported => write!(f, "The node does not support presets"),
+            PresetError::InvalidPreset(msg) => write!(f, "Invalid preset: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
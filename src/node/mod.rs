@@ -1,8 +1,13 @@
 pub mod builtin;
+pub mod error;
+mod node_registry;
+
+pub use node_registry::NodeRegistry;
 
 use crate::{
     data_types::{AudioContext, TypeInfo},
     graph::error::NodeError,
+    node::error::PresetError,
 };
 use std::any::Any;
 
@@ -31,12 +36,43 @@ pub trait Node: Send + Any {
     /// Updates the node with the given audio context.
     fn update(&mut self, audio_ctx: &AudioContext);
 
-    /// Prepares the node for processing.
+    /// Prepares the node for processing. Allocate the buffers and the state used while processing here.
     fn prepare(&mut self) -> Result<(), Box<dyn NodeError>>;
 
     /// Processes the given input pointer and writes the output to the output pointer.
+    /// Called on the audio thread during playback, so it must not allocate or lock.
     fn process(&mut self, inputs: &[*const u8], outputs: &[*mut u8], audio_ctx: &AudioContext);
 
+    /// Returns the name of the node type, such as "LfoNode". Defaults to the name of the struct without the module path.
+    fn get_type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    /// Returns the latency in samples which the node introduces to the audio. Defaults to zero.
+    fn get_latency(&self) -> usize {
+        0
+    }
+
+    /// Returns whether the output may change over time even if the inputs don't change,
+    /// such as an oscillator or a node with an internal state. Defaults to false.
+    /// `Graph::process_from` processes the time variant nodes on every call, so stateful nodes must return true.
+    fn is_time_variant(&self) -> bool {
+        false
+    }
+
+    /// Returns the parameters of the node serialized as a preset.
+    /// Nodes without parameters return None, which is the default.
+    fn export_preset(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores the parameters of the node from a preset returned by `export_preset`.
+    /// The node must be prepared again after importing a preset.
+    fn import_preset(&mut self, _preset: &[u8]) -> Result<(), PresetError> {
+        Err(PresetError::Unsupported)
+    }
+
     /// Converts a reference to the node to any.
     fn as_any(&self) -> &dyn Any;
 
@@ -0,0 +1,64 @@
+use crate::node::{
+    Node,
+    builtin::{
+        AnalyzerNode, ArithmeticNode, AudioInputNode, AudioOutputNode, CompressorNode,
+        ConvolutionNode, FeedbackReturnNode, FeedbackSendNode, LfoNode, NoteInputNode, PannerNode,
+        ParametricEqNode, ReverbNode, WaveshaperNode,
+    },
+};
+use std::collections::HashMap;
+
+/// Creates nodes from their type names, which is how the nodes are restored from a project file.
+/// The builtin nodes are registered by default. Register custom nodes before loading a project which uses them.
+#[derive(Clone)]
+pub struct NodeRegistry {
+    constructors: HashMap<&'static str, fn() -> Box<dyn Node>>,
+}
+
+impl NodeRegistry {
+    /// Creates a registry with only the builtin nodes.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            constructors: HashMap::new(),
+        };
+        registry.register::<AnalyzerNode>();
+        registry.register::<ArithmeticNode>();
+        registry.register::<AudioInputNode>();
+        registry.register::<AudioOutputNode>();
+        registry.register::<CompressorNode>();
+        registry.register::<ConvolutionNode>();
+        registry.register::<FeedbackReturnNode>();
+        registry.register::<FeedbackSendNode>();
+        registry.register::<LfoNode>();
+        registry.register::<NoteInputNode>();
+        registry.register::<PannerNode>();
+        registry.register::<ParametricEqNode>();
+        registry.register::<ReverbNode>();
+        registry.register::<WaveshaperNode>();
+        registry
+    }
+
+    /// Registers the node type under the name returned by its `get_type_name`.
+    /// The node is created with `Default`, and its parameters are restored from the saved preset.
+    pub fn register<N: Node + Default>(&mut self) {
+        let type_name = N::default().get_type_name();
+        self.constructors.insert(type_name, create_node::<N>);
+    }
+
+    /// Creates a node of the given type name, or returns None if the type is not registered.
+    pub fn create(&self, type_name: &str) -> Option<Box<dyn Node>> {
+        self.constructors
+            .get(type_name)
+            .map(|constructor| constructor())
+    }
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn create_node<N: Node + Default>() -> Box<dyn Node> {
+    Box::new(N::default())
+}
@@ -1,7 +1,7 @@
 use crate::{
     data_types::Beats,
     graph::error::GraphError,
-    mixer::{Project, TrackID},
+    mixer::{DitherConfig, Monitor, Project, TrackID},
 };
 use midir::MidiInputPort;
 
@@ -12,8 +12,16 @@ pub enum AudioCommand {
     Seek(Beats),
     UpdateProject(Box<Project>),
     ExportAudio(Box<Project>),
+    /// Stops the running exports, which report `AudioResult::ExportCancelled`.
+    CancelExport,
     ArmTrack(TrackID),
     DisarmTrack,
+    /// Sets how the output is quantized for monitoring.
+    SetOutputDither(DitherConfig),
+    /// Sets whether the NaN and infinite samples of the output are replaced with silence.
+    SetSanitizeOutput(bool),
+    /// Sets the track auditioned in isolation on the output, or None to output the whole mix.
+    SetMonitor(Option<Monitor>),
 }
 
 #[derive(Clone)]
@@ -25,10 +33,14 @@ pub enum MidiCommand {
 #[derive(Clone)]
 pub enum AudioResult {
     ExportedAudio(Vec<f32>),
+    /// The progress of the running export, with the beat being rendered and the rendered fraction from 0.0 to 1.0.
+    ExportProgress(Beats, f32),
+    ExportCancelled,
 }
 
 pub enum AudioError {
     GraphError(GraphError),
+    RenderError(TrackID, GraphError),
     PlayStreamError(cpal::PlayStreamError),
     CommandFailed(AudioCommand),
 }
@@ -1,6 +1,7 @@
 use crate::{
     data_types::{AudioContext, MidiEvent},
-    mixer::{Mixer, Project, TrackID},
+    graph::error::GraphError,
+    mixer::{MAX_RENDER_ERRORS, Mixer, PlaybackEndAction, Project, TrackID},
     thread::{AudioCommand, AudioError, AudioResult, export},
     track::note_track::NoteTrack,
 };
@@ -17,25 +18,37 @@ use std::sync::{
     mpsc,
 };
 
+/// The ringbufs between the audio callback and the other threads, which the callback uses without blocking.
+pub(super) struct AudioRingbufs {
+    /// The MIDI events sent from the MIDI thread.
+    pub(super) midi_consumer: ringbuf::HeapCons<MidiEvent>,
+    /// The VU levels sent to the host.
+    pub(super) vu_producer: ringbuf::HeapProd<f32>,
+    /// The errors of the tracks which newly failed to render, sent to the host.
+    pub(super) render_error_producer: ringbuf::HeapProd<(TrackID, GraphError)>,
+}
+
 pub(super) struct OutputCallbackState {
     pub(super) playhead: Arc<AtomicUsize>,
     pub(super) is_playing: Arc<AtomicBool>,
+    pub(super) sanitized_samples: Arc<AtomicUsize>,
 }
 
 struct OutputCallbackContext {
     mixer: Mixer,
     consumer: Caching<Arc<SharedRb<Heap<AudioCommand>>>, false, true>,
-    midi_consumer: ringbuf::HeapCons<MidiEvent>,
-    vu_producer: ringbuf::HeapProd<f32>,
+    ringbufs: AudioRingbufs,
     pending_project: Arc<Mutex<Option<Project>>>,
+    /// The project replaced by the last applied project, dropped outside the audio thread.
+    retired_project: Arc<Mutex<Option<Project>>>,
 }
 
 pub(super) fn audio_thread(
     command_rx: mpsc::Receiver<AudioCommand>,
     result_tx: mpsc::Sender<Result<AudioResult, AudioError>>,
-    midi_consumer: ringbuf::HeapCons<MidiEvent>,
-    vu_producer: ringbuf::HeapProd<f32>,
+    ringbufs: AudioRingbufs,
     playhead: Arc<AtomicUsize>,
+    sanitized_samples: Arc<AtomicUsize>,
     audio_ctx: AudioContext,
     initial_project: Project,
 ) {
@@ -44,11 +57,15 @@ pub(super) fn audio_thread(
     // Create a mixer with the given initial project
     let pending_project = Arc::new(Mutex::new(None));
     let pending_arc = Arc::clone(&pending_project);
+    let retired_project = Arc::new(Mutex::new(None));
     let mixer = Mixer::new(initial_project);
 
     // Create a generation variable to track the latest prepared project
     let generation = Arc::new(AtomicUsize::new(0));
 
+    // Create a flag to cancel the running exports
+    let export_cancelled = Arc::new(AtomicBool::new(false));
+
     // Get a cpal device
     let host = cpal::default_host();
     let device = host
@@ -68,14 +85,15 @@ pub(super) fn audio_thread(
     let callback_state = OutputCallbackState {
         playhead,
         is_playing: is_playing_clone,
+        sanitized_samples,
     };
     let stream = output_callback(
         OutputCallbackContext {
             mixer,
             consumer,
-            midi_consumer,
-            vu_producer,
+            ringbufs,
             pending_project: pending_arc,
+            retired_project: Arc::clone(&retired_project),
         },
         device,
         config,
@@ -109,6 +127,7 @@ pub(super) fn audio_thread(
                 let current_gen = generation.fetch_add(1, Ordering::SeqCst) + 1;
                 let gen_arc = Arc::clone(&generation);
                 let pending_arc = Arc::clone(&pending_project);
+                let retired_arc = Arc::clone(&retired_project);
                 let result_tx = result_tx.clone();
                 std::thread::spawn(move || {
                     // Prepare the project before applying the project
@@ -121,12 +140,31 @@ pub(super) fn audio_thread(
                     if gen_arc.load(Ordering::SeqCst) == current_gen {
                         // Send the new project to the audio playback thread
                         *pending_arc.lock().unwrap() = Some(*new_project);
+                    } else {
+                        return;
+                    }
+
+                    // Free the replaced projects here instead of on the audio thread until the project is applied,
+                    // or until a newer update takes over. The audio thread only applies a project when the slot is empty
+                    loop {
+                        let applied = pending_arc.lock().unwrap().is_none();
+                        // Release the lock before dropping the project, so the audio thread never waits for it
+                        let retired = retired_arc.lock().unwrap().take();
+                        drop(retired);
+                        if applied || gen_arc.load(Ordering::SeqCst) != current_gen {
+                            break;
+                        }
+                        std::thread::sleep(std::time::Duration::from_millis(1));
                     }
                 });
             }
             AudioCommand::ExportAudio(project) => {
                 let result_tx = result_tx.clone();
-                export::spawn_export_thread(result_tx, *project);
+                export_cancelled.store(false, Ordering::Release);
+                export::spawn_export_thread(result_tx, *project, Arc::clone(&export_cancelled));
+            }
+            AudioCommand::CancelExport => {
+                export_cancelled.store(true, Ordering::Release);
             }
             AudioCommand::ArmTrack(_) => {
                 if let Err(command) = producer.try_push(command) {
@@ -135,7 +173,10 @@ pub(super) fn audio_thread(
                         .unwrap();
                 }
             }
-            AudioCommand::DisarmTrack => {
+            AudioCommand::DisarmTrack
+            | AudioCommand::SetOutputDither(_)
+            | AudioCommand::SetSanitizeOutput(_)
+            | AudioCommand::SetMonitor(_) => {
                 if let Err(command) = producer.try_push(command) {
                     result_tx
                         .send(Err(AudioError::CommandFailed(command)))
@@ -155,6 +196,9 @@ fn output_callback(
     state: OutputCallbackState,
 ) -> cpal::Stream {
     let mut armed_track: Option<TrackID> = None;
+    // The tracks which failed in the last callback and in this callback, swapped every callback so they never grow
+    let mut failed_tracks: Vec<TrackID> = Vec::with_capacity(MAX_RENDER_ERRORS);
+    let mut newly_failed: Vec<TrackID> = Vec::with_capacity(MAX_RENDER_ERRORS);
 
     device
         .build_output_stream(
@@ -162,11 +206,14 @@ fn output_callback(
             move |data: &mut [f32], _| {
                 let mut current_playhead = state.playhead.load(Ordering::Relaxed);
 
-                // Get the project without blocking
-                if let Ok(mut pending) = context.pending_project.try_lock()
+                // Get the project without blocking. The project is only applied when the old project can be
+                // handed over to be freed by the update thread, otherwise it stays pending until the next callback
+                if let Ok(mut retired) = context.retired_project.try_lock()
+                    && retired.is_none()
+                    && let Ok(mut pending) = context.pending_project.try_lock()
                     && let Some(new_project) = pending.take()
                 {
-                    context.mixer.apply_project(new_project, current_playhead);
+                    *retired = Some(context.mixer.apply_project(new_project, current_playhead));
                 }
 
                 // Process all pending commands from the audio command ringbuf
@@ -185,24 +232,62 @@ fn output_callback(
                         AudioCommand::DisarmTrack => {
                             armed_track = None;
                         }
+                        AudioCommand::SetOutputDither(config) => {
+                            context.mixer.set_output_dither(config);
+                        }
+                        AudioCommand::SetSanitizeOutput(sanitize_output) => {
+                            context.mixer.set_sanitize_output(sanitize_output);
+                        }
+                        AudioCommand::SetMonitor(monitor) => {
+                            context.mixer.set_monitor(monitor);
+                        }
                         _ => {}
                     }
                 }
 
-                // Drain MIDI events and pass them to the armed NoteTrack
-                let midi_events: Vec<MidiEvent> = context.midi_consumer.pop_iter().collect();
-                if !midi_events.is_empty()
-                    && let Some(track_id) = armed_track
-                    && let Some(track) = context.mixer.project.tracks.get_mut(&track_id)
-                    && let Some(note_track) = track.as_any_mut().downcast_mut::<NoteTrack>()
-                {
-                    note_track.pass_midi(&midi_events);
+                // Drain MIDI events and pass them to the armed NoteTrack one by one, so draining doesn't allocate
+                let mut note_track = armed_track
+                    .and_then(|track_id| context.mixer.project.tracks.get_mut(&track_id))
+                    .and_then(|track| track.as_any_mut().downcast_mut::<NoteTrack>());
+                while let Some(event) = context.ringbufs.midi_consumer.try_pop() {
+                    if let Some(note_track) = note_track.as_mut() {
+                        note_track.pass_midi(std::slice::from_ref(&event));
+                    }
                 }
 
                 let is_playing = state.is_playing.load(Ordering::Relaxed);
 
+                // The playhead held at the end of the range doesn't render the same chunk again
+                let project = &context.mixer.project;
+                let is_held = project.playback_end_action == PlaybackEndAction::Hold
+                    && current_playhead >= project.get_range_end_samples();
+
                 // Process the audio and fill the output buffer
-                context.mixer.process(is_playing, current_playhead, data);
+                context
+                    .mixer
+                    .process(is_playing && !is_held, current_playhead, data);
+
+                // Report the errors of the tracks which newly failed to render
+                // The errors are dropped if the host doesn't keep up with the ringbuf
+                newly_failed.clear();
+                for (track_id, err) in context.mixer.take_render_errors() {
+                    if !failed_tracks.contains(&track_id) {
+                        let _ = context
+                            .ringbufs
+                            .render_error_producer
+                            .try_push((track_id, err));
+                    }
+                    newly_failed.push(track_id);
+                }
+                std::mem::swap(&mut failed_tracks, &mut newly_failed);
+
+                // Report the samples replaced by the output sanitizing without allocating
+                let sanitized = context.mixer.take_sanitized_count();
+                if sanitized > 0 {
+                    state
+                        .sanitized_samples
+                        .fetch_add(sanitized, Ordering::Relaxed);
+                }
 
                 // Send the generated waveform data to the main thread for visualization
                 let channels = context.mixer.project.audio_ctx.channels;
@@ -215,14 +300,36 @@ fn output_callback(
                         .sum::<f32>()
                         / (data.len() / channels) as f32)
                         .sqrt();
-                    context.vu_producer.try_push(rms).ok();
+                    context.ringbufs.vu_producer.try_push(rms).ok();
                 }
 
                 if is_playing {
-                    state.playhead.fetch_add(
-                        context.mixer.project.audio_ctx.buffer_size,
-                        Ordering::Relaxed,
-                    );
+                    let project = &context.mixer.project;
+                    let next_playhead = current_playhead + project.audio_ctx.buffer_size;
+                    let range_end = project.get_range_end_samples();
+
+                    // Handle the end of the range according to the playback end action
+                    if current_playhead < range_end && next_playhead >= range_end {
+                        match project.playback_end_action {
+                            PlaybackEndAction::Stop => {
+                                state.is_playing.store(false, Ordering::Release);
+                                state.playhead.store(range_end, Ordering::Relaxed);
+                            }
+                            PlaybackEndAction::Loop => {
+                                let range_start =
+                                    project.tempo_map.beats_to_samples(project.range_start);
+                                state.playhead.store(range_start, Ordering::Relaxed);
+                                context.mixer.seek(range_start);
+                            }
+                            PlaybackEndAction::Hold => {
+                                state.playhead.store(range_end, Ordering::Relaxed);
+                            }
+                        }
+                    } else if next_playhead < range_end
+                        || project.playback_end_action != PlaybackEndAction::Hold
+                    {
+                        state.playhead.store(next_playhead, Ordering::Relaxed);
+                    }
                 }
             },
             |err| {
@@ -1,23 +1,35 @@
 use crate::{
-    mixer::{Mixer, Project},
+    mixer::{Dither, Mixer, Project},
     thread::{AudioError, AudioResult},
 };
-use std::{sync::mpsc, thread};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+};
 
+/// Spawns a thread to render the range of the project, which reports the progress in every percent,
+/// and the exported audio at the end. The export stops when the cancel flag is set.
 pub(super) fn spawn_export_thread(
     result_tx: mpsc::Sender<Result<AudioResult, AudioError>>,
     mut project: Project,
+    cancelled: Arc<AtomicBool>,
 ) {
     thread::spawn(move || {
-        if let Err(err) = project.prepare() {
+        if let Err(err) = project.prepare_for_export() {
             result_tx.send(Err(AudioError::GraphError(err))).unwrap();
             return;
         }
 
         let start_sample = project.tempo_map.beats_to_samples(project.range_start);
-        let end_sample = start_sample + project.tempo_map.beats_to_samples(project.range_duration);
+        let end_sample = project.get_range_end_samples();
         let buffer_size = project.audio_ctx.buffer_size;
         let channels = project.audio_ctx.channels;
+        let bit_depth = project.export_bit_depth;
+        let dither = project.export_dither;
 
         let mut mixer = Mixer::new(project);
         mixer.seek(start_sample);
@@ -26,12 +38,51 @@ pub(super) fn spawn_export_thread(
         let mut output: Vec<f32> = Vec::with_capacity(total_samples);
         let mut buf = vec![0.0f32; buffer_size * channels];
         let mut playhead = start_sample;
+        let mut render_errors = Vec::new();
+        let mut reported_percent = 0;
 
         while playhead < end_sample {
+            // Stop rendering if the export is cancelled
+            if cancelled.load(Ordering::Acquire) {
+                result_tx.send(Ok(AudioResult::ExportCancelled)).unwrap();
+                return;
+            }
+
             mixer.process(true, playhead, &mut buf);
+            // Keep the first error of each track
+            for (track_id, err) in mixer.take_render_errors() {
+                if !render_errors.iter().any(|(id, _)| *id == track_id) {
+                    render_errors.push((track_id, err));
+                }
+            }
             let frames = (end_sample - playhead).min(buffer_size);
             output.extend_from_slice(&buf[..frames * channels]);
             playhead += frames;
+
+            // Report the progress whenever another percent is rendered
+            let progress = (playhead - start_sample) as f32 / (end_sample - start_sample) as f32;
+            let percent = (progress * 100.0) as usize;
+            if percent > reported_percent {
+                reported_percent = percent;
+                result_tx
+                    .send(Ok(AudioResult::ExportProgress(
+                        mixer.project.tempo_map.samples_to_beats(playhead),
+                        progress,
+                    )))
+                    .unwrap();
+            }
+        }
+
+        // Quantize the audio to the export bit depth
+        if let Some(bits) = bit_depth.integer_bits() {
+            Dither::default().quantize(&mut output, bits, dither);
+        }
+
+        // Report the tracks which failed to render
+        for (track_id, err) in render_errors {
+            result_tx
+                .send(Err(AudioError::RenderError(track_id, err)))
+                .unwrap();
         }
 
         result_tx
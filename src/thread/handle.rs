@@ -1,5 +1,13 @@
-use crate::thread::{AudioCommand, AudioError, AudioResult, audio_command::MidiCommand};
-use std::sync::{Arc, atomic::AtomicUsize, mpsc};
+use crate::{
+    graph::error::GraphError,
+    mixer::TrackID,
+    thread::{AudioCommand, AudioError, AudioResult, audio_command::MidiCommand},
+};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+    mpsc,
+};
 
 /// A struct to communicate with the audio thread.
 pub struct AudioThreadHandle {
@@ -7,5 +15,24 @@ pub struct AudioThreadHandle {
     pub midi_command_tx: mpsc::Sender<MidiCommand>,
     pub result_rx: mpsc::Receiver<Result<AudioResult, AudioError>>,
     pub vu_consumer: ringbuf::HeapCons<f32>,
+    /// The errors of the tracks which newly failed to render during the playback.
+    /// The export reports its errors through `result_rx` instead.
+    pub render_error_consumer: ringbuf::HeapCons<(TrackID, GraphError)>,
     pub playhead: Arc<AtomicUsize>,
+    /// The number of NaN or infinite samples replaced in the output since it was last taken.
+    pub sanitized_samples: Arc<AtomicUsize>,
+}
+
+impl AudioThreadHandle {
+    /// Returns the current playhead position in samples.
+    /// The audio thread only stores the position to an atomic, so the host can poll this from a UI timer to follow the playback.
+    pub fn get_playhead(&self) -> usize {
+        self.playhead.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of NaN or infinite samples replaced in the output since the last call, and resets it.
+    /// The audio thread only adds to an atomic, so the count can be polled like the playhead.
+    pub fn take_sanitized_count(&self) -> usize {
+        self.sanitized_samples.swap(0, Ordering::Relaxed)
+    }
 }
@@ -9,7 +9,9 @@ pub use handle::AudioThreadHandle;
 
 use crate::{
     data_types::{AudioContext, MidiEvent},
-    mixer::Project,
+    graph::error::GraphError,
+    mixer::{MAX_RENDER_ERRORS, Project, TrackID},
+    thread::audio_thread::AudioRingbufs,
 };
 use ringbuf::{HeapRb, traits::Split};
 use std::{
@@ -29,10 +31,21 @@ impl AudioThread {
         // Shared playhead position using Arc and AtomicUsize for thread-safe access.
         let playhead = Arc::new(AtomicUsize::new(0));
         let playhead_clone = playhead.clone();
+        // The count of the samples replaced by the output sanitizing, added to by the audio thread.
+        let sanitized_samples = Arc::new(AtomicUsize::new(0));
+        let sanitized_samples_clone = sanitized_samples.clone();
         // A ringbuf to send MIDI events to the audio thread from the midi thread.
         let (midi_producer, midi_consumer) = HeapRb::<MidiEvent>::new(64).split();
         // A ringbuf to send the calculated VU levels to the host.
         let (vu_producer, vu_consumer) = HeapRb::<f32>::new(audio_ctx.channels * 2).split();
+        // A ringbuf to send the render errors to the host without allocating on the audio thread.
+        let (render_error_producer, render_error_consumer) =
+            HeapRb::<(TrackID, GraphError)>::new(MAX_RENDER_ERRORS).split();
+        let ringbufs = AudioRingbufs {
+            midi_consumer,
+            vu_producer,
+            render_error_producer,
+        };
 
         // --- MAIN AUDIO THREAD ---
         thread::spawn(move || {
@@ -44,9 +57,9 @@ impl AudioThread {
             audio_thread::audio_thread(
                 audio_command_rx,
                 result_tx,
-                midi_consumer,
-                vu_producer,
+                ringbufs,
                 playhead_clone,
+                sanitized_samples_clone,
                 audio_ctx,
                 initial_project,
             );
@@ -60,7 +73,9 @@ impl AudioThread {
             midi_command_tx,
             result_rx,
             vu_consumer,
+            render_error_consumer,
             playhead,
+            sanitized_samples,
         }
     }
 }
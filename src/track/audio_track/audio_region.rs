@@ -1,10 +1,22 @@
-use crate::data_types::Beats;
+use crate::{
+    audio_utils::{deinterleave, interleave},
+    data_types::Beats,
+    mixer::{MIN_BPM, TempoMap},
+    track::audio_track::{
+        InterpolationMode, RemixError, SourceStorage, resampler::resample_channels,
+        tempo_strech::map_channels,
+    },
+};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Stores the raw audio source data.
+/// Serialized with the samples, unless the storage refers to an external file.
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "SerializedAudioRegion", from = "SerializedAudioRegion")]
 pub struct AudioRegion {
-    pub data: Vec<f32>,
+    /// Interleaved samples of the source, shared between the clones of the region.
+    pub data: Arc<Vec<f32>>,
     pub frames: usize,
     pub sample_rate: u32,
     pub channels: u16,
@@ -12,10 +24,126 @@ pub struct AudioRegion {
     pub start: Beats,
     pub duration: Beats,
     pub max_duration: Beats,
+    /// The position in the source where the region starts playing, in beats at the base BPM.
+    pub source_offset: Beats,
+    /// Whether to apply short fades at the edges of the region to prevent clicks.
+    pub declick: bool,
+    /// Whether to loop the source from the source offset when the region is longer than the source.
+    pub loop_source: bool,
+    /// The length of the fade in at the start of the region.
+    pub fade_in: Beats,
+    /// The length of the fade out at the end of the region.
+    pub fade_out: Beats,
+    /// Where the samples are stored when the region is saved.
+    pub storage: SourceStorage,
+    /// Whether each channel plays with inverted polarity. Channels past the end are not inverted.
+    pub inverted_channels: Vec<bool>,
+    /// How the source is interpolated when the region plays at a different rate than the source.
+    pub interpolation: InterpolationMode,
+    /// Whether the region follows the tempo map, stretching the source to the beats.
+    /// The regions not locked to the tempo play the source at its own rate and keep their time in seconds when the tempo changes.
+    pub tempo_locked: bool,
+}
+
+/// The saved form of an audio region, which omits the samples of the regions referring to external files.
+#[derive(Clone, Serialize, Deserialize)]
+struct SerializedAudioRegion {
+    #[serde(default)]
+    data: Option<Arc<Vec<f32>>>,
+    frames: usize,
+    sample_rate: u32,
+    channels: u16,
+    base_bpm: f64,
+    start: Beats,
+    duration: Beats,
+    max_duration: Beats,
+    #[serde(default)]
+    source_offset: Beats,
+    #[serde(default = "default_declick")]
+    declick: bool,
+    #[serde(default)]
+    loop_source: bool,
+    #[serde(default)]
+    fade_in: Beats,
+    #[serde(default)]
+    fade_out: Beats,
+    #[serde(default)]
+    storage: SourceStorage,
+    #[serde(default)]
+    inverted_channels: Vec<bool>,
+    #[serde(default)]
+    interpolation: InterpolationMode,
+    #[serde(default = "default_tempo_locked")]
+    tempo_locked: bool,
+}
+
+fn default_declick() -> bool {
+    true
+}
+
+fn default_tempo_locked() -> bool {
+    true
+}
+
+impl From<AudioRegion> for SerializedAudioRegion {
+    fn from(region: AudioRegion) -> Self {
+        let data = match region.storage {
+            SourceStorage::Embedded => Some(region.data),
+            SourceStorage::External(_) => None,
+        };
+        Self {
+            data,
+            frames: region.frames,
+            sample_rate: region.sample_rate,
+            channels: region.channels,
+            base_bpm: region.base_bpm,
+            start: region.start,
+            duration: region.duration,
+            max_duration: region.max_duration,
+            source_offset: region.source_offset,
+            declick: region.declick,
+            loop_source: region.loop_source,
+            fade_in: region.fade_in,
+            fade_out: region.fade_out,
+            storage: region.storage,
+            inverted_channels: region.inverted_channels,
+            interpolation: region.interpolation,
+            tempo_locked: region.tempo_locked,
+        }
+    }
+}
+
+impl From<SerializedAudioRegion> for AudioRegion {
+    fn from(region: SerializedAudioRegion) -> Self {
+        // A region without the samples has an empty source until the host sets the samples
+        let (data, frames) = match region.data {
+            Some(data) => (data, region.frames),
+            None => (Arc::new(Vec::new()), 0),
+        };
+        Self {
+            data,
+            frames,
+            sample_rate: region.sample_rate,
+            channels: region.channels,
+            base_bpm: region.base_bpm,
+            start: region.start,
+            duration: region.duration,
+            max_duration: region.max_duration,
+            source_offset: region.source_offset,
+            declick: region.declick,
+            loop_source: region.loop_source,
+            fade_in: region.fade_in,
+            fade_out: region.fade_out,
+            storage: region.storage,
+            inverted_channels: region.inverted_channels,
+            interpolation: region.interpolation,
+            tempo_locked: region.tempo_locked,
+        }
+    }
 }
 
 impl AudioRegion {
-    /// Create a new audio region with zeros.
+    /// Create a new audio region with zeros. The base BPM is clamped to `MIN_BPM`.
     pub fn zeros(
         frames: usize,
         sample_rate: u32,
@@ -25,14 +153,705 @@ impl AudioRegion {
         duration: Beats,
     ) -> Self {
         Self {
-            data: vec![0.0; frames * channels as usize],
+            data: Arc::new(vec![0.0; frames * channels as usize]),
             frames,
             sample_rate,
             channels,
-            base_bpm,
+            base_bpm: base_bpm.max(MIN_BPM),
             start,
             duration,
             max_duration: duration,
+            source_offset: Beats(0.0),
+            declick: true,
+            loop_source: false,
+            fade_in: Beats(0.0),
+            fade_out: Beats(0.0),
+            storage: SourceStorage::Embedded,
+            inverted_channels: Vec::new(),
+            interpolation: InterpolationMode::default(),
+            tempo_locked: true,
         }
     }
+
+    /// Creates a new audio region playing the whole source given as planar channels, one buffer per channel.
+    /// Channels shorter than the longest channel are padded with zeros. The base BPM is clamped to `MIN_BPM`.
+    pub fn from_planar(
+        channels: &[Vec<f32>],
+        sample_rate: u32,
+        base_bpm: f64,
+        start: Beats,
+    ) -> Self {
+        let mut region = Self::zeros(
+            0,
+            sample_rate,
+            channels.len() as u16,
+            base_bpm,
+            start,
+            Beats(0.0),
+        );
+        region.set_data(interleave(channels));
+        region.max_duration = region.get_source_beats(region.base_bpm);
+        region.duration = region.max_duration;
+        region
+    }
+
+    /// Returns a copy of the source as planar channels, one buffer per channel.
+    /// The source is stored interleaved, which `data` holds directly.
+    pub fn to_planar(&self) -> Vec<Vec<f32>> {
+        deinterleave(&self.data, self.channels as usize)
+    }
+
+    /// Replaces the source with the given interleaved samples.
+    pub fn set_data(&mut self, data: Vec<f32>) {
+        self.frames = data.len() / (self.channels as usize).max(1);
+        self.data = Arc::new(data);
+    }
+
+    /// Replaces the source with another take, stretching it to fill the same length as the current source.
+    /// The start, the duration, the max duration, the source offset, the fades and the flags are preserved.
+    /// The samples, the frames, the sample rate, the channels and the base BPM are recomputed from the new source.
+    /// An empty source keeps the current base BPM.
+    pub fn replace_source_preserving_timing(
+        &mut self,
+        data: Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+    ) {
+        self.sample_rate = sample_rate;
+        self.channels = channels;
+        self.set_data(data);
+
+        // Change the base BPM so that the new source spans the same beats as the old one
+        let seconds = self.get_source_seconds();
+        if seconds > 0.0 && self.max_duration.0 > 0.0 {
+            self.base_bpm = (self.max_duration.0 / seconds * 60.0).max(MIN_BPM);
+        }
+    }
+
+    /// Sets where the samples are stored when the region is saved.
+    /// Refer to an external file to keep large sources out of the saved project.
+    pub fn set_storage(&mut self, storage: SourceStorage) {
+        self.storage = storage;
+    }
+
+    /// Sets the position in the source where the region starts playing.
+    /// An offset beyond the source length makes the region silent.
+    pub fn set_source_offset(&mut self, source_offset: Beats) {
+        self.source_offset = source_offset.max(Beats(0.0));
+    }
+
+    /// Sets whether the region follows the tempo map.
+    /// A region not locked to the tempo keeps its pitch and its time in seconds when the tempo changes.
+    pub fn set_tempo_locked(&mut self, tempo_locked: bool) {
+        self.tempo_locked = tempo_locked;
+    }
+
+    /// Sets whether to apply short fades at the edges of the region to prevent clicks.
+    pub fn set_declick(&mut self, declick: bool) {
+        self.declick = declick;
+    }
+
+    /// Sets whether to loop the source when the region is longer than the source.
+    /// The playback wraps back to the source offset after reaching the end of the source, instead of playing silence.
+    pub fn set_loop_source(&mut self, loop_source: bool) {
+        self.loop_source = loop_source;
+    }
+
+    /// Sets the length of the fade in at the start of the region.
+    pub fn set_fade_in(&mut self, fade_in: Beats) {
+        self.fade_in = fade_in.max(Beats(0.0));
+    }
+
+    /// Sets the length of the fade out at the end of the region.
+    pub fn set_fade_out(&mut self, fade_out: Beats) {
+        self.fade_out = fade_out.max(Beats(0.0));
+    }
+
+    /// Sets how the source is interpolated when the region plays at a different rate than the source.
+    /// A cheaper mode suits interactive stretching, and `Cubic` suits the final playback. Defaults to `Linear`.
+    pub fn set_interpolation(&mut self, interpolation: InterpolationMode) {
+        self.interpolation = interpolation;
+    }
+
+    /// Sets whether the channel plays with inverted polarity, leaving the other channels as they are.
+    /// The source is left untouched, and the polarity is applied when the track renders the region.
+    /// Does nothing if the channel is out of range.
+    pub fn set_channel_polarity(&mut self, channel: usize, inverted: bool) {
+        if channel >= self.channels as usize {
+            return;
+        }
+        if self.inverted_channels.len() <= channel {
+            self.inverted_channels.resize(channel + 1, false);
+        }
+        self.inverted_channels[channel] = inverted;
+    }
+
+    /// Returns whether the channel plays with inverted polarity.
+    pub fn is_channel_inverted(&self, channel: usize) -> bool {
+        self.inverted_channels
+            .get(channel)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Returns the frame where the region starts playing the source, and the number of frames looped
+    /// from there to the end of the source. The loop length is 0 if the offset is beyond the source.
+    pub(crate) fn get_loop_frames(&self) -> (usize, usize) {
+        let loop_start =
+            (self.source_offset.to_seconds(self.base_bpm) * self.sample_rate as f64) as usize;
+        (loop_start, self.frames.saturating_sub(loop_start))
+    }
+
+    // --- SOURCE EDITING ---
+
+    /// Appends the audio of the other region after the source of this region,
+    /// converting it to the sample rate and the channels of this region.
+    /// The channels are up or down mixed like a region rendered into a track with a different channel count.
+    /// The duration of the region is extended by the appended length.
+    pub fn append(&mut self, other: &AudioRegion) {
+        // Mix the other region to the channels of this region before resampling it
+        let remixed;
+        let other = if other.channels == self.channels {
+            other
+        } else {
+            let other_channels = other.channels as usize;
+            let matrix: Vec<Vec<f32>> = map_channels(other_channels, self.channels as usize)
+                .into_iter()
+                .map(|sources| {
+                    let mut gains = vec![0.0; other_channels];
+                    for (source, gain) in sources {
+                        gains[source] += gain;
+                    }
+                    gains
+                })
+                .collect();
+            // The matrix is only empty if this region has no channels, which can't hold any audio
+            let Ok(region) = other.remix(&matrix) else {
+                return;
+            };
+            remixed = region;
+            &remixed
+        };
+
+        let appended = if other.sample_rate == self.sample_rate {
+            other.data.to_vec()
+        } else {
+            resample_channels(
+                &other.data,
+                other.frames,
+                other.sample_rate as usize,
+                other.channels as usize,
+                self.sample_rate as usize,
+                self.channels as usize,
+            )
+        };
+        let appended_frames = appended.len() / (self.channels as usize).max(1);
+
+        // Extend the source and the duration
+        // Copy the source only if it is shared with other regions
+        Arc::make_mut(&mut self.data).extend(appended);
+        self.frames += appended_frames;
+        let appended_beats = Beats::from_seconds(
+            appended_frames as f64 / self.sample_rate as f64,
+            self.base_bpm,
+        );
+        self.max_duration = self.max_duration + appended_beats;
+        self.duration = self.duration + appended_beats;
+    }
+
+    /// Concatenates the regions into a new region, which has the timing, sample rate and channels of the first region.
+    /// Returns None if no regions are given.
+    pub fn concat(regions: &[AudioRegion]) -> Option<AudioRegion> {
+        let (first, rest) = regions.split_first()?;
+        let mut concatenated = first.clone();
+        for region in rest {
+            concatenated.append(region);
+        }
+        Some(concatenated)
+    }
+
+    // --- SAMPLE RATE CONVERSION ---
+
+    /// Returns a copy of the region with the source resampled to the sample rate, such as the sample rate of the project.
+    /// The source spans the same seconds, so the timing of the region is preserved.
+    /// Useful to match the sources to the sample rate of the project when importing them.
+    pub fn to_sample_rate(&self, sample_rate: u32) -> AudioRegion {
+        if sample_rate == self.sample_rate {
+            return self.clone();
+        }
+        let resampled = resample_channels(
+            &self.data,
+            self.frames,
+            self.sample_rate as usize,
+            self.channels as usize,
+            sample_rate as usize,
+            self.channels as usize,
+        );
+
+        let mut region = self.clone();
+        region.sample_rate = sample_rate;
+        region.set_data(resampled);
+        region
+    }
+
+    // --- CHANNEL REMIXING ---
+
+    /// Returns a copy of the region with the channels mixed by the matrix, which has a row of gains for each output channel.
+    /// Each output sample is the sum of the input samples of the frame multiplied by the gains in the row.
+    /// Returns an error if the matrix is empty or a row doesn't have a gain for every channel of the source.
+    pub fn remix(&self, matrix: &[Vec<f32>]) -> Result<AudioRegion, RemixError> {
+        let channels = self.channels as usize;
+        if matrix.is_empty() {
+            return Err(RemixError::EmptyMatrix);
+        }
+        if let Some((row, gains)) = matrix
+            .iter()
+            .enumerate()
+            .find(|(_, gains)| gains.len() != channels)
+        {
+            return Err(RemixError::ChannelMismatch(row, channels, gains.len()));
+        }
+
+        let mut remixed = Vec::with_capacity(self.frames * matrix.len());
+        for frame in self.data.chunks_exact(channels.max(1)) {
+            remixed.extend(
+                matrix
+                    .iter()
+                    .map(|gains| gains.iter().zip(frame).map(|(g, s)| g * s).sum::<f32>()),
+            );
+        }
+
+        let mut region = self.clone();
+        region.channels = matrix.len() as u16;
+        region.set_data(remixed);
+        Ok(region)
+    }
+
+    /// Returns a mono copy of the region, averaging all channels.
+    pub fn to_mono(&self) -> AudioRegion {
+        let channels = (self.channels as usize).max(1);
+        let matrix = [vec![1.0 / channels as f32; channels]];
+        self.remix(&matrix).unwrap_or_else(|_| self.clone())
+    }
+
+    /// Returns a stereo copy of the region. A mono source is copied to both channels,
+    /// and a source with more channels keeps its first two channels.
+    /// Use `remix` with a fold-down matrix to mix the other channels of surround sources in.
+    pub fn to_stereo(&self) -> AudioRegion {
+        let channels = (self.channels as usize).max(1);
+        let matrix: Vec<Vec<f32>> = (0..2)
+            .map(|output| {
+                (0..channels)
+                    .map(|input| {
+                        if input == output.min(channels - 1) {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        self.remix(&matrix).unwrap_or_else(|_| self.clone())
+    }
+
+    /// Removes the leading and trailing frames whose samples are all below the threshold in dBFS.
+    /// The duration is shortened if it exceeds the trimmed source. An all silent source is trimmed to empty.
+    pub fn trim_silence(&mut self, threshold_dbfs: f32) {
+        let threshold = 10f32.powf(threshold_dbfs / 20.0);
+        let channels = (self.channels as usize).max(1);
+        let is_audible = |frame: &[f32]| frame.iter().any(|s| s.abs() >= threshold);
+
+        let first = self.data.chunks_exact(channels).position(is_audible);
+        let last = self.data.chunks_exact(channels).rposition(is_audible);
+        let trimmed = match (first, last) {
+            (Some(first), Some(last)) => {
+                self.data[first * channels..(last + 1) * channels].to_vec()
+            }
+            _ => Vec::new(),
+        };
+        self.set_data(trimmed);
+
+        // Shorten the region to the trimmed source
+        self.max_duration = self.get_source_beats(self.base_bpm);
+        self.duration = self.duration.min(self.max_duration);
+    }
+
+    /// Subtracts the mean of each channel from its samples to remove the DC offset.
+    /// Channels without an offset, such as all-zero channels, are left untouched.
+    pub fn remove_dc_offset(&mut self) {
+        let offsets = self.dc_offset();
+        if offsets.iter().all(|offset| *offset == 0.0) {
+            return;
+        }
+        let channels = (self.channels as usize).max(1);
+        for frame in Arc::make_mut(&mut self.data).chunks_exact_mut(channels) {
+            for (sample, offset) in frame.iter_mut().zip(offsets.iter()) {
+                *sample -= offset;
+            }
+        }
+    }
+
+    /// Negates every sample of the source, inverting the polarity of all channels.
+    pub fn invert_phase(&mut self) {
+        for sample in Arc::make_mut(&mut self.data).iter_mut() {
+            *sample = -*sample;
+        }
+    }
+
+    // --- SPLITTING ---
+
+    /// Splits the region at the given beats, shortening this region and returning the latter part.
+    /// The latter part plays the source from where this region stops, so the two parts play the same audio as before.
+    /// Returns None if the beats is not inside the region.
+    pub fn split_at(&mut self, at: Beats) -> Option<AudioRegion> {
+        if at <= self.start || at >= self.get_end() {
+            return None;
+        }
+        let mut latter = self.clone();
+        latter.start = at;
+        latter.duration = self.get_end() - at;
+        latter.source_offset = self.source_offset + (at - self.start);
+        // Keep the latter region inside the loop, so it does not start past the end of the source
+        if self.loop_source {
+            let loop_beats = Beats::from_seconds(
+                self.get_loop_frames().1 as f64 / self.sample_rate as f64,
+                self.base_bpm,
+            );
+            if loop_beats.0 > 0.0 {
+                let looped = (latter.source_offset - self.source_offset).0 % loop_beats.0;
+                latter.source_offset = self.source_offset + Beats(looped);
+            }
+        }
+        // The fade in stays at the start of the former region, and the fade out at the end of the latter region
+        latter.fade_in = Beats(0.0);
+        self.fade_out = Beats(0.0);
+        self.duration = at - self.start;
+        Some(latter)
+    }
+
+    /// Returns whether the other region directly follows this region and continues playing the same source.
+    pub fn can_merge(&self, other: &AudioRegion) -> bool {
+        // Allow a small error caused by splitting the beats
+        let is_adjacent = (self.get_end().0 - other.start.0).abs() < 1e-9;
+        let is_continuous =
+            ((self.source_offset + self.duration).0 - other.source_offset.0).abs() < 1e-9;
+        is_adjacent
+            && is_continuous
+            && self.sample_rate == other.sample_rate
+            && self.channels == other.channels
+            && self.base_bpm == other.base_bpm
+            && self.loop_source == other.loop_source
+            && self.tempo_locked == other.tempo_locked
+            && (0..self.channels as usize).all(|channel| {
+                self.is_channel_inverted(channel) == other.is_channel_inverted(channel)
+            })
+            && (Arc::ptr_eq(&self.data, &other.data) || self.data == other.data)
+    }
+
+    // --- TIMING ---
+
+    /// Returns the length of the whole source in seconds, or 0.0 if the source is empty.
+    pub fn get_source_seconds(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.frames as f64 / self.sample_rate as f64
+    }
+
+    /// Returns the length of the whole source in beats at the given tempo.
+    pub fn get_source_beats(&self, bpm: f64) -> Beats {
+        Beats::from_seconds(self.get_source_seconds(), bpm)
+    }
+
+    /// Returns the end beats of the region.
+    pub fn get_end(&self) -> Beats {
+        self.start + self.duration
+    }
+
+    /// Moves the region for the new tempo map if it is not locked to the tempo, keeping its start and length in seconds.
+    /// The regions locked to the tempo keep their beats and are stretched to the new tempo when the track is prepared.
+    pub fn apply_tempo_change(&mut self, old_tempo_map: &TempoMap, new_tempo_map: &TempoMap) {
+        if self.tempo_locked {
+            return;
+        }
+        // Converts the beats from the region start to the beats at the new tempo
+        let start_seconds = old_tempo_map.beats_to_seconds(self.start);
+        let new_start = new_tempo_map.seconds_to_beats(start_seconds);
+        let retime = |beats: Beats| {
+            let seconds = old_tempo_map.beats_to_seconds(self.start + beats) - start_seconds;
+            new_tempo_map.seconds_to_beats(start_seconds + seconds) - new_start
+        };
+        self.duration = retime(self.duration);
+        self.max_duration = retime(self.max_duration);
+        self.fade_in = retime(self.fade_in);
+        self.fade_out = retime(self.fade_out);
+        self.start = new_start;
+    }
+
+    /// Returns whether the region plays at the given beats, which is from the start up to the end exclusive.
+    pub fn is_active_at(&self, at: Beats) -> bool {
+        self.start <= at && at < self.get_end()
+    }
+
+    /// Returns whether the region overlaps the range between the given beats,
+    /// including when the range contains the whole region.
+    pub fn is_active_between(&self, start: Beats, end: Beats) -> bool {
+        start < self.get_end() && end > self.start
+    }
+
+    // --- COMPARISON ---
+
+    /// Returns whether the sources have the same sample rate, channels and length,
+    /// and every pair of samples differs by at most the epsilon. The timing of the regions is not compared.
+    pub fn approx_eq(&self, other: &AudioRegion, epsilon: f32) -> bool {
+        self.sample_rate == other.sample_rate
+            && self.channels == other.channels
+            && self.data.len() == other.data.len()
+            && self.max_abs_diff(other) <= epsilon
+    }
+
+    /// Returns the largest absolute difference between the samples of the sources.
+    /// Only the samples up to the end of the shorter source are compared. NaN samples count as an infinite difference.
+    pub fn max_abs_diff(&self, other: &AudioRegion) -> f32 {
+        self.data
+            .iter()
+            .zip(other.data.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, |max, diff| {
+                if diff.is_nan() {
+                    f32::INFINITY
+                } else {
+                    max.max(diff)
+                }
+            })
+    }
+
+    // --- ANALYSIS ---
+
+    /// Returns the maximum absolute sample value across all channels, or 0.0 if the region is empty.
+    pub fn peak(&self) -> f32 {
+        self.data.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    /// Returns the root mean square of all samples, or 0.0 if the region is empty.
+    pub fn rms(&self) -> f32 {
+        if self.data.is_empty() {
+            return 0.0;
+        }
+        (self.data.iter().map(|s| s * s).sum::<f32>() / self.data.len() as f32).sqrt()
+    }
+
+    /// Returns the maximum absolute sample value of each channel.
+    pub fn channel_peaks(&self) -> Vec<f32> {
+        let channels = self.channels as usize;
+        let mut peaks = vec![0.0f32; channels];
+        for frame in self.data.chunks_exact(channels.max(1)) {
+            for (peak, s) in peaks.iter_mut().zip(frame) {
+                *peak = peak.max(s.abs());
+            }
+        }
+        peaks
+    }
+
+    /// Returns the root mean square of each channel.
+    pub fn channel_rms(&self) -> Vec<f32> {
+        let channels = self.channels as usize;
+        let mut sums = vec![0.0f32; channels];
+        for frame in self.data.chunks_exact(channels.max(1)) {
+            for (sum, s) in sums.iter_mut().zip(frame) {
+                *sum += s * s;
+            }
+        }
+        let frames = self.data.len() / channels.max(1);
+        if frames == 0 {
+            return sums;
+        }
+        sums.iter()
+            .map(|sum| (sum / frames as f32).sqrt())
+            .collect()
+    }
+
+    /// Returns the DC offset of each channel, which is the mean of its samples.
+    pub fn dc_offset(&self) -> Vec<f32> {
+        let channels = self.channels as usize;
+        let mut sums = vec![0.0f64; channels];
+        for frame in self.data.chunks_exact(channels.max(1)) {
+            for (sum, s) in sums.iter_mut().zip(frame) {
+                *sum += *s as f64;
+            }
+        }
+        let frames = self.data.len() / channels.max(1);
+        if frames == 0 {
+            return vec![0.0; channels];
+        }
+        sums.iter()
+            .map(|sum| (sum / frames as f64) as f32)
+            .collect()
+    }
+
+    /// Returns the Pearson correlation between the left and the right channel over the whole source.
+    /// 1 means mono, 0 means decorrelated and -1 means fully out of phase.
+    /// Returns None if the region is not stereo.
+    pub fn stereo_correlation(&self) -> Option<f32> {
+        self.stereo_correlation_between(0, self.frames)
+    }
+
+    /// Returns the Pearson correlation between the left and the right channel between the start and the end frame.
+    /// Returns 0 if either channel is constant, and None if the region is not stereo.
+    pub fn stereo_correlation_between(&self, start: usize, end: usize) -> Option<f32> {
+        if self.channels != 2 {
+            return None;
+        }
+        let frames = self.data.len() / 2;
+        let end = end.min(frames);
+        let start = start.min(end);
+        if start == end {
+            return Some(0.0);
+        }
+        let samples = &self.data[start * 2..end * 2];
+        let count = (end - start) as f64;
+
+        // Calculate the means of the channels
+        let (sum_left, sum_right) = samples.chunks_exact(2).fold((0.0, 0.0), |(l, r), frame| {
+            (l + frame[0] as f64, r + frame[1] as f64)
+        });
+        let (mean_left, mean_right) = (sum_left / count, sum_right / count);
+
+        // Calculate the covariance and the variances
+        let (mut covariance, mut variance_left, mut variance_right) = (0.0, 0.0, 0.0);
+        for frame in samples.chunks_exact(2) {
+            let left = frame[0] as f64 - mean_left;
+            let right = frame[1] as f64 - mean_right;
+            covariance += left * right;
+            variance_left += left * left;
+            variance_right += right * right;
+        }
+
+        let denominator = (variance_left * variance_right).sqrt();
+        if denominator == 0.0 {
+            return Some(0.0);
+        }
+        Some((covariance / denominator) as f32)
+    }
+
+    /// Returns up to `max_points` points of (side, mid) evenly taken from the source to draw a goniometer.
+    /// Returns None if the region is not stereo.
+    pub fn goniometer_points(&self, max_points: usize) -> Option<Vec<(f32, f32)>> {
+        if self.channels != 2 {
+            return None;
+        }
+        let frames = self.data.len() / 2;
+        let step = frames.div_ceil(max_points.max(1)).max(1);
+        Some(
+            self.data
+                .chunks_exact(2)
+                .step_by(step)
+                .take(max_points)
+                .map(|frame| {
+                    let side = (frame[0] - frame[1]) * std::f32::consts::FRAC_1_SQRT_2;
+                    let mid = (frame[0] + frame[1]) * std::f32::consts::FRAC_1_SQRT_2;
+                    (side, mid)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the frame ranges where all samples are below the threshold in dBFS for at least `min_frames` frames.
+    /// Each range is the start frame and the end frame, exclusive.
+    pub fn find_silence_regions(
+        &self,
+        threshold_dbfs: f32,
+        min_frames: usize,
+    ) -> Vec<(usize, usize)> {
+        let threshold = 10f32.powf(threshold_dbfs / 20.0);
+        let channels = (self.channels as usize).max(1);
+        let mut regions = Vec::new();
+        let mut silence_start = None;
+
+        for (frame, samples) in self.data.chunks_exact(channels).enumerate() {
+            let is_silent = samples.iter().all(|s| s.abs() < threshold);
+            match (is_silent, silence_start) {
+                (true, None) => silence_start = Some(frame),
+                (false, Some(start)) => {
+                    if frame - start >= min_frames {
+                        regions.push((start, frame));
+                    }
+                    silence_start = None;
+                }
+                _ => {}
+            }
+        }
+
+        // Close the silence which lasts until the end
+        let frames = self.data.len() / channels;
+        if let Some(start) = silence_start
+            && frames - start >= min_frames
+        {
+            regions.push((start, frames));
+        }
+
+        regions
+    }
+
+    /// Returns the minimum and maximum sample values across all channels for each bucket,
+    /// dividing the region into the given number of buckets to draw a waveform overview.
+    /// Returns one bucket per frame if the bucket count exceeds the number of frames.
+    pub fn waveform_peaks(&self, buckets: usize) -> Vec<(f32, f32)> {
+        let channels = (self.channels as usize).max(1);
+        let frames = self.data.len() / channels;
+        let bucket_count = buckets.min(frames);
+
+        (0..bucket_count)
+            .map(|bucket| {
+                // Calculate the frame range of the bucket
+                let start = bucket * frames / bucket_count;
+                let end = (bucket + 1) * frames / bucket_count;
+                self.data[start * channels..end * channels]
+                    .iter()
+                    .fold((f32::MAX, f32::MIN), |(min, max), s| {
+                        (min.min(*s), max.max(*s))
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_copies_mono_to_every_channel() {
+        let mut region = AudioRegion::from_planar(&[vec![0.1], vec![0.2]], 4, 60.0, Beats(0.0));
+        let mono = AudioRegion::from_planar(&[vec![0.3, 0.4]], 4, 60.0, Beats(0.0));
+        region.append(&mono);
+
+        assert_eq!(region.frames, 3);
+        assert_eq!(*region.data, [0.1, 0.2, 0.3, 0.3, 0.4, 0.4]);
+        assert_eq!(region.duration, Beats(0.75));
+    }
+
+    #[test]
+    fn append_averages_extra_channels() {
+        let mut region = AudioRegion::from_planar(&[vec![0.1]], 4, 60.0, Beats(0.0));
+        let stereo =
+            AudioRegion::from_planar(&[vec![0.2, 0.6], vec![0.4, 0.0]], 4, 60.0, Beats(0.0));
+        region.append(&stereo);
+
+        assert_eq!(region.frames, 3);
+        assert_eq!(*region.data, [0.1, 0.3, 0.3]);
+    }
+
+    #[test]
+    fn concat_converts_to_the_first_region() {
+        let first = AudioRegion::from_planar(&[vec![0.0; 4], vec![0.0; 4]], 4, 60.0, Beats(0.0));
+        let second = AudioRegion::from_planar(&[vec![0.5; 8]], 8, 60.0, Beats(0.0));
+        let concatenated = AudioRegion::concat(&[first, second]).unwrap();
+
+        assert_eq!(concatenated.channels, 2);
+        assert_eq!(concatenated.sample_rate, 4);
+        assert_eq!(concatenated.frames, 8);
+        assert!(concatenated.data[8..].iter().all(|sample| *sample == 0.5));
+    }
 }
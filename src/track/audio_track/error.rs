@@ -0,0 +1,25 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum RemixError {
+    /// The matrix has no rows, so it produces no channels.
+    EmptyMatrix,
+    /// The row of the matrix has a different number of gains from the channels of the source.
+    /// (Row index, channels of the source, gains in the row)
+    ChannelMismatch(usize, usize, usize),
+}
+
+impl Display for RemixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemixError::EmptyMatrix => write!(f, "The mixing matrix has no output channels"),
+            RemixError::ChannelMismatch(row, expected, found) => write!(
+                f,
+                "Row {} of the mixing matrix has {} gains, but the source has {} channels",
+                row, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RemixError {}
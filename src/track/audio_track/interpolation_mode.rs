@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// How the source of an audio region is interpolated between its frames when the region is played at a different rate.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InterpolationMode {
+    /// Reads the nearest frame. The cheapest, but aliases audibly.
+    Nearest,
+    /// Interpolates linearly between the two surrounding frames.
+    #[default]
+    Linear,
+    /// Interpolates with a cubic Hermite curve through the four surrounding frames, which dulls the highs less.
+    Cubic,
+}
+
+impl InterpolationMode {
+    /// Interpolates between `y1` and `y2` at the fraction `t` from 0.0 to 1.0, where `y0` precedes `y1` and `y3` follows `y2`.
+    pub fn interpolate(&self, y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+        match self {
+            InterpolationMode::Nearest => {
+                if t < 0.5 {
+                    y1
+                } else {
+                    y2
+                }
+            }
+            InterpolationMode::Linear => y1 * (1.0 - t) + y2 * t,
+            InterpolationMode::Cubic => {
+                // Catmull-Rom spline
+                let c1 = 0.5 * (y2 - y0);
+                let c2 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+                let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
+                ((c3 * t + c2) * t + c1) * t + y1
+            }
+        }
+    }
+}
@@ -1,19 +1,33 @@
 mod audio_region;
+mod error;
+mod interpolation_mode;
 mod resampler;
+mod source_storage;
 mod tempo_strech;
 
 pub use audio_region::AudioRegion;
+pub use error::RemixError;
+pub use interpolation_mode::InterpolationMode;
+pub use resampler::VariableResampler;
+pub use source_storage::SourceStorage;
 
 use crate::{
+    audio_utils::apply_gain,
     data_types::{AudioContext, Beats},
     graph::{Graph, error::GraphError},
     mixer::TempoMap,
     node::builtin::{AudioInputNode, AudioOutputNode},
-    track::{RegionID, Track, audio_track::tempo_strech::tempo_strech},
+    track::{
+        CollisionPolicy, FreezeCache, RegionID, Track, audio_track::tempo_strech::tempo_strech,
+        push_regions,
+    },
 };
 use std::collections::HashMap;
 
-#[derive(Default, Clone)]
+/// The length of the fades applied at the edges of the regions to prevent clicks, in milliseconds.
+const DECLICK_MS: f64 = 5.0;
+
+#[derive(Clone)]
 pub struct AudioTrack {
     // --- GRAPH ---
     graph: Graph,
@@ -21,6 +35,20 @@ pub struct AudioTrack {
     // --- RAW AUDIO DATA ---
     regions: HashMap<RegionID, AudioRegion>,
     processed: Vec<f32>,
+    /// The frame where the processed audio starts, which is the start of the range.
+    processed_start: usize,
+    /// A buffer to pad the end of the processed audio with zeros, allocated in `prepare`.
+    tail_buffer: Vec<f32>,
+
+    // --- FREEZING ---
+    freeze_cache: FreezeCache,
+
+    // --- EDITING ---
+    collision_policy: CollisionPolicy,
+
+    // --- INPUT ---
+    /// The gain applied to the region audio before the graph.
+    input_gain: f32,
 
     // --- AUDIO CONTEXT ---
     audio_ctx: AudioContext,
@@ -29,16 +57,37 @@ pub struct AudioTrack {
     next_region_id: usize,
 }
 
+impl Default for AudioTrack {
+    fn default() -> Self {
+        Self {
+            graph: Graph::default(),
+            regions: HashMap::new(),
+            processed: Vec::new(),
+            processed_start: 0,
+            tail_buffer: Vec::new(),
+            freeze_cache: FreezeCache::default(),
+            collision_policy: CollisionPolicy::default(),
+            // The region audio passes at unity gain unless set otherwise
+            input_gain: 1.0,
+            audio_ctx: AudioContext::default(),
+            next_region_id: 0,
+        }
+    }
+}
+
 impl AudioTrack {
     pub fn new(audio_ctx: AudioContext) -> Self {
         // Create a graph with the input and output nodes
         let input_node = AudioInputNode::default();
         let output_node = AudioOutputNode::default();
-        let graph = Graph::new(
+        let mut graph = Graph::new(
             Box::new(input_node),
             Box::new(output_node),
             audio_ctx.clone(),
         );
+        // Connect the input to the output so the region audio passes through an empty graph
+        // Both nodes carry the same audio type, so the edge is always valid
+        graph.add_edge_unchecked((graph.get_input_id(), 0, graph.get_output_id(), 0));
 
         Self {
             graph,
@@ -67,21 +116,88 @@ impl AudioTrack {
         self.next_region_id = next_id;
     }
 
+    /// Returns the ID which will be given to the next added region.
+    pub fn get_next_region_id(&self) -> usize {
+        self.next_region_id
+    }
+
     fn generate_region_id(&mut self) -> RegionID {
         let id = RegionID(self.next_region_id);
         self.next_region_id += 1;
         id
     }
 
+    /// Adds the region to the track, resolving overlaps with the collision policy, and returns the ID of the region.
     pub fn add_region(&mut self, region: AudioRegion) -> RegionID {
         let id = self.generate_region_id();
         self.regions.insert(id, region);
+        self.resolve_collisions(&id);
         id
     }
 
     pub fn set_regions(&mut self, regions: HashMap<RegionID, AudioRegion>) {
         self.regions = regions;
     }
+
+    // --- COLLISION RESOLVING ---
+
+    /// Resolves the overlaps between the region and the other regions with the collision policy.
+    fn resolve_collisions(&mut self, id: &RegionID) {
+        let Some(region) = self.regions.get(id) else {
+            return;
+        };
+        let (start, end) = (region.start, region.get_end());
+
+        match self.collision_policy {
+            CollisionPolicy::Overlap => {}
+            CollisionPolicy::Push => push_regions(
+                start,
+                end,
+                self.regions
+                    .iter_mut()
+                    .filter(|(other_id, _)| *other_id != id)
+                    .map(|(_, other)| (&mut other.start, other.duration)),
+            ),
+            CollisionPolicy::Crossfade => {
+                let (mut fade_in, mut fade_out) = (None, None);
+                for (_, other) in self
+                    .regions
+                    .iter_mut()
+                    .filter(|(other_id, _)| *other_id != id)
+                {
+                    if !other.is_active_between(start, end) {
+                        continue;
+                    }
+                    // A region inside the other region leaves no room for the fades, so the regions overlap
+                    let other_end = other.get_end();
+                    let is_nested = (other.start <= start && end <= other_end)
+                        || (start <= other.start && other_end <= end);
+                    if is_nested {
+                        continue;
+                    }
+                    if other.start < start {
+                        // The other region fades out while this region fades in
+                        let overlap = other_end - start;
+                        other.set_fade_out(overlap);
+                        fade_in = fade_in.max(Some(overlap));
+                    } else {
+                        // This region fades out while the other region fades in
+                        let overlap = end - other.start;
+                        other.set_fade_in(overlap);
+                        fade_out = fade_out.max(Some(overlap));
+                    }
+                }
+                if let Some(region) = self.regions.get_mut(id) {
+                    if let Some(fade_in) = fade_in {
+                        region.set_fade_in(fade_in);
+                    }
+                    if let Some(fade_out) = fade_out {
+                        region.set_fade_out(fade_out);
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl Track for AudioTrack {
@@ -98,40 +214,135 @@ impl Track for AudioTrack {
     }
 
     fn get_graph_mut(&mut self) -> &mut Graph {
+        // The graph may be modified, so the frozen output must be rendered again
+        self.freeze_cache.invalidate();
         &mut self.graph
     }
 
     // --- GRAPH UPDATING ---
 
     fn set_graph(&mut self, graph: Graph) {
+        self.freeze_cache.invalidate();
         self.graph = graph;
     }
 
     // --- AUDIO CONTEXT UPDARING ---
 
     fn set_audio_ctx(&mut self, audio_ctx: &AudioContext) {
+        self.freeze_cache.invalidate();
         self.audio_ctx = audio_ctx.clone();
         self.graph.set_audio_ctx(audio_ctx);
     }
 
+    fn on_tempo_changed(&mut self, old_tempo_map: &TempoMap, new_tempo_map: &TempoMap) {
+        self.freeze_cache.invalidate();
+        for region in self.regions.values_mut() {
+            region.apply_tempo_change(old_tempo_map, new_tempo_map);
+        }
+    }
+
+    // --- FREEZING ---
+
+    fn freeze(&mut self) {
+        self.freeze_cache.freeze();
+    }
+
+    fn unfreeze(&mut self) {
+        self.freeze_cache.unfreeze();
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.freeze_cache.is_frozen()
+    }
+
     // --- REGION MODIFICATION ---
 
     fn move_region(&mut self, region_id: &RegionID, new_start: Beats) {
         if let Some(region) = self.regions.get_mut(region_id) {
             region.start = new_start;
         }
+        self.resolve_collisions(region_id);
+    }
+
+    fn set_input_gain(&mut self, gain: f32) {
+        self.input_gain = gain;
+    }
+
+    fn get_input_gain(&self) -> f32 {
+        self.input_gain
+    }
+
+    fn set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+    }
+
+    fn get_collision_policy(&self) -> CollisionPolicy {
+        self.collision_policy
     }
 
     fn set_region_duration(&mut self, region_id: &RegionID, new_duration: Beats) {
         if let Some(region) = self.regions.get_mut(region_id) {
             region.duration = new_duration;
         }
+        self.resolve_collisions(region_id);
     }
 
     fn remove_region(&mut self, region_id: &RegionID) {
         self.regions.remove(region_id);
     }
 
+    fn get_regions_at(&self, at: Beats) -> Vec<RegionID> {
+        let mut ids: Vec<RegionID> = self
+            .regions
+            .iter()
+            .filter(|(_, region)| region.is_active_at(at))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    fn get_regions_in_range(&self, start: Beats, end: Beats) -> Vec<RegionID> {
+        let mut ids: Vec<RegionID> = self
+            .regions
+            .iter()
+            .filter(|(_, region)| region.is_active_between(start, end))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    fn split_region(&mut self, region_id: &RegionID, at: Beats) -> Option<(RegionID, RegionID)> {
+        let latter = self.regions.get_mut(region_id)?.split_at(at)?;
+        let latter_id = self.add_region(latter);
+        Some((*region_id, latter_id))
+    }
+
+    fn merge_regions(&mut self, first: &RegionID, second: &RegionID) -> bool {
+        if first == second {
+            return false;
+        }
+        let (Some(first_region), Some(second_region)) =
+            (self.regions.get(first), self.regions.get(second))
+        else {
+            return false;
+        };
+        if !first_region.can_merge(second_region) {
+            return false;
+        }
+
+        // Extend the first region over the second region
+        let Some(second_region) = self.regions.remove(second) else {
+            return false;
+        };
+        if let Some(first_region) = self.regions.get_mut(first) {
+            first_region.duration = second_region.get_end() - first_region.start;
+            first_region.fade_out = second_region.fade_out;
+        }
+        true
+    }
+
     // --- SEEKING ---
 
     fn seek(&mut self, _playhead: usize) {}
@@ -140,67 +351,143 @@ impl Track for AudioTrack {
 
     fn prepare(
         &mut self,
-        _start: usize,
+        start: usize,
         duration: usize,
         tempo_map: &TempoMap,
     ) -> Result<(), GraphError> {
-        // Calculate the total sample number
+        let channels = self.audio_ctx.channels;
+
+        // Calculate the total sample number of the range
         // Ceil to a multiple of the buffer size
         let total_frames =
             duration.div_ceil(self.audio_ctx.buffer_size) * self.audio_ctx.buffer_size;
-        // Initialize the processed vector with zeros
-        self.processed = vec![0.0; total_frames * self.audio_ctx.channels];
+        // Initialize the processed vector with zeros, which covers the range from the start
+        let mut processed = vec![0.0; total_frames * channels];
+        self.tail_buffer = vec![0.0; self.audio_ctx.buffer_size * channels];
 
         // Resample the each regions
         for region in self.regions.values() {
-            let resampled = tempo_strech(
-                region,
-                self.audio_ctx.sample_rate,
-                self.audio_ctx.channels,
-                tempo_map,
+            let mut resampled =
+                tempo_strech(region, self.audio_ctx.sample_rate, channels, tempo_map);
+
+            // Apply the fades of the region
+            let fade_frames = |from: Beats, length: Beats| {
+                tempo_map
+                    .beats_to_samples(from + length)
+                    .saturating_sub(tempo_map.beats_to_samples(from))
+            };
+            apply_fades(
+                &mut resampled,
+                channels,
+                fade_frames(region.start, region.fade_in),
+                fade_frames(region.get_end() - region.fade_out, region.fade_out),
             );
 
-            // Calculate the start sample index of the buffer
-            let region_start_index = tempo_map.beats_to_samples(region.start);
+            // Fade the edges of the region in and out
+            if region.declick {
+                let fade_frames = (DECLICK_MS * 0.001 * self.audio_ctx.sample_rate as f64) as usize;
+                apply_declick(&mut resampled, channels, fade_frames);
+            }
+
+            // Calculate the start frame of the region relative to the range start
+            let region_start = tempo_map.beats_to_samples(region.start) as isize - start as isize;
+            // Skip the part of the region before the range start
+            let src_start = (-region_start).max(0) as usize * channels;
+            let dst_start = region_start.max(0) as usize * channels;
+            if src_start >= resampled.len() || dst_start >= processed.len() {
+                continue;
+            }
 
             // Add the resampled samples
-            let available = self.processed.len().saturating_sub(region_start_index);
-            let copy_len = resampled.len().min(available);
-            for (i, sample) in resampled[..copy_len].iter().enumerate() {
-                self.processed[region_start_index + i] += sample;
+            let copy_len = (resampled.len() - src_start).min(processed.len() - dst_start);
+            for (dst, src) in processed[dst_start..dst_start + copy_len]
+                .iter_mut()
+                .zip(resampled[src_start..src_start + copy_len].iter())
+            {
+                *dst += *src;
             }
         }
 
+        // Apply the input gain before the graph
+        if self.input_gain != 1.0 {
+            apply_gain(&mut processed, self.input_gain);
+        }
+
+        // The frozen output is outdated if the regions, the tempo, the range or the input gain changed
+        if processed != self.processed || start != self.processed_start {
+            self.freeze_cache.invalidate();
+        }
+        self.processed = processed;
+        self.processed_start = start;
+
         // Then prepare the graph
-        self.graph.prepare()
+        self.graph.prepare()?;
+
+        // Render the whole track through the graph if it is frozen
+        if self.freeze_cache.needs_render() {
+            let chunk_len = self.audio_ctx.buffer_size * self.audio_ctx.channels;
+            let mut rendered = vec![0.0; self.processed.len()];
+            for (input, output) in self
+                .processed
+                .chunks_exact(chunk_len)
+                .zip(rendered.chunks_exact_mut(chunk_len))
+            {
+                self.graph.process(
+                    &[input.as_ptr() as *const u8],
+                    &[output.as_mut_ptr() as *mut u8],
+                )?;
+            }
+            self.freeze_cache.set_rendered(rendered);
+
+            // Prepare the graph again to reset the node state after rendering
+            self.graph.prepare()?;
+        }
+
+        Ok(())
     }
 
-    fn process(&mut self, is_playing: bool, playhead: usize, output: &mut [f32]) {
+    fn process(
+        &mut self,
+        is_playing: bool,
+        playhead: usize,
+        output: &mut [f32],
+    ) -> Result<(), GraphError> {
         if is_playing {
-            let buffer_size = self.audio_ctx.buffer_size * self.audio_ctx.channels;
-            let buffer_end = playhead + buffer_size;
+            // Convert the playhead to the sample index in the processed audio
+            // Playheads before the range start are treated as the end of the audio, which reads silence
+            let index = playhead
+                .checked_sub(self.processed_start)
+                .map_or(self.processed.len(), |frame| {
+                    (frame * self.audio_ctx.channels).min(self.processed.len())
+                });
+
+            // Play back the rendered output if the track is frozen
+            if self.freeze_cache.read(index, output) {
+                return Ok(());
+            }
 
-            // Create a vector for input buffer
-            let mut input_vec: Vec<f32>;
+            let buffer_size = self.audio_ctx.buffer_size * self.audio_ctx.channels;
+            let buffer_end = index + buffer_size;
 
             let input_ptr = if buffer_end <= self.processed.len() {
                 // Get a pointer to the input buffer
-                self.processed[playhead..buffer_end].as_ptr() as *const u8
+                self.processed[index..buffer_end].as_ptr() as *const u8
             } else {
                 // If the audio data for the buffer is partially unavailable fill the rest with zero
-                let available = self.processed.len().saturating_sub(playhead);
-                input_vec = vec![0f32; buffer_size];
-                if available > 0 {
-                    input_vec[..available]
-                        .copy_from_slice(&self.processed[playhead..playhead + available]);
-                }
-                input_vec.as_ptr() as *const u8
+                let available = self.processed.len() - index;
+                self.tail_buffer.resize(buffer_size, 0.0);
+                self.tail_buffer[available..].fill(0.0);
+                self.tail_buffer[..available]
+                    .copy_from_slice(&self.processed[index..index + available]);
+                self.tail_buffer.as_ptr() as *const u8
             };
 
             // Process the graph
             self.graph
-                .process(&[input_ptr], &[output.as_mut_ptr() as *mut u8]);
+                .process(&[input_ptr], &[output.as_mut_ptr() as *mut u8])?;
         }
+
+        Ok(())
     }
 
     // --- ANY CASTING ---
@@ -213,3 +500,135 @@ impl Track for AudioTrack {
         self
     }
 }
+
+/// Applies linear fades to the first and the last frames of the interleaved samples.
+/// The fades are shortened to half of the samples if the samples are too short.
+fn apply_declick(samples: &mut [f32], channels: usize, fade_frames: usize) {
+    let frames = samples.len() / channels.max(1);
+    let fade_frames = fade_frames.min(frames / 2);
+    apply_fades(samples, channels, fade_frames, fade_frames);
+}
+
+/// Applies a linear fade in and a linear fade out of the given frames to the interleaved samples.
+/// The fades are shortened to the length of the samples.
+fn apply_fades(
+    samples: &mut [f32],
+    channels: usize,
+    fade_in_frames: usize,
+    fade_out_frames: usize,
+) {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+
+    let fade_in_frames = fade_in_frames.min(frames);
+    for frame in 0..fade_in_frames {
+        let gain = frame as f32 / fade_in_frames as f32;
+        for sample in &mut samples[frame * channels..(frame + 1) * channels] {
+            *sample *= gain;
+        }
+    }
+
+    let fade_out_frames = fade_out_frames.min(frames);
+    for frame in 0..fade_out_frames {
+        let gain = frame as f32 / fade_out_frames as f32;
+        let tail = frames - 1 - frame;
+        for sample in &mut samples[tail * channels..(tail + 1) * channels] {
+            *sample *= gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audio_ctx() -> AudioContext {
+        AudioContext {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: 64,
+            max_voices: 1,
+        }
+    }
+
+    #[test]
+    fn new_track_renders_the_regions_through_the_graph() {
+        let audio_ctx = audio_ctx();
+        let tempo_map = TempoMap::new(audio_ctx.clone(), 120.0);
+        let mut track = AudioTrack::new(audio_ctx.clone());
+        track.add_region(AudioRegion::from_planar(
+            &[vec![0.5; 48000], vec![-0.5; 48000]],
+            48000,
+            120.0,
+            Beats(0.0),
+        ));
+        track.prepare(0, 48000, &tempo_map).unwrap();
+
+        // Read a buffer after the declick fade, where the region plays at full level
+        let mut output = vec![0.0; audio_ctx.buffer_size * audio_ctx.channels];
+        track.process(true, 24000, &mut output).unwrap();
+        for frame in output.chunks_exact(2) {
+            assert_eq!(frame, [0.5, -0.5]);
+        }
+    }
+
+    #[test]
+    fn default_track_has_unity_input_gain() {
+        assert_eq!(AudioTrack::default().get_input_gain(), 1.0);
+        assert_eq!(AudioTrack::new(audio_ctx()).get_input_gain(), 1.0);
+    }
+
+    #[test]
+    fn new_track_is_silent_without_regions() {
+        let audio_ctx = audio_ctx();
+        let tempo_map = TempoMap::new(audio_ctx.clone(), 120.0);
+        let mut track = AudioTrack::new(audio_ctx.clone());
+        track.prepare(0, 48000, &tempo_map).unwrap();
+
+        // The output node adds to the output, which the mixer clears before each track
+        let mut output = vec![0.0; audio_ctx.buffer_size * audio_ctx.channels];
+        track.process(true, 0, &mut output).unwrap();
+        assert!(output.iter().all(|sample| *sample == 0.0));
+    }
+
+    /// Returns a silent region lasting the given beats at 60 BPM.
+    fn region(start: f64, duration: f64) -> AudioRegion {
+        let frames = (duration * 100.0) as usize;
+        AudioRegion::from_planar(&[vec![0.0; frames]], 100, 60.0, Beats(start))
+    }
+
+    #[test]
+    fn crossfade_fades_the_overlapping_regions() {
+        let mut track = AudioTrack::new(audio_ctx());
+        track.set_collision_policy(CollisionPolicy::Crossfade);
+        let first = track.add_region(region(0.0, 4.0));
+        let second = track.add_region(region(3.0, 4.0));
+
+        assert_eq!(track.get_region(&first).unwrap().fade_out, Beats(1.0));
+        assert_eq!(track.get_region(&second).unwrap().fade_in, Beats(1.0));
+    }
+
+    #[test]
+    fn crossfade_leaves_nested_regions_overlapped() {
+        let mut track = AudioTrack::new(audio_ctx());
+        track.set_collision_policy(CollisionPolicy::Crossfade);
+        let outer = track.add_region(region(0.0, 8.0));
+        let inner = track.add_region(region(2.0, 2.0));
+
+        for id in [outer, inner] {
+            let region = track.get_region(&id).unwrap();
+            assert_eq!((region.fade_in, region.fade_out), (Beats(0.0), Beats(0.0)));
+        }
+    }
+
+    #[test]
+    fn lengthening_a_region_pushes_the_following_regions() {
+        let mut track = AudioTrack::new(audio_ctx());
+        track.set_collision_policy(CollisionPolicy::Push);
+        let first = track.add_region(region(0.0, 2.0));
+        let second = track.add_region(region(2.0, 2.0));
+        track.set_region_duration(&first, Beats(3.0));
+
+        assert_eq!(track.get_region(&second).unwrap().start, Beats(3.0));
+    }
+}
@@ -6,22 +6,29 @@ pub fn resample_channels(
     target_sample_rate: usize,
     target_channels: usize,
 ) -> Vec<f32> {
-    // Calculate the ratio of the source and the target sample rate
-    let ratio = source_sample_rate as f32 / target_sample_rate as f32;
-    let mut read_pos = 0.0;
-    let mut output = Vec::new();
+    // A zero sample rate has no ratio to scale the length by
+    if source_sample_rate == 0 || target_sample_rate == 0 {
+        return Vec::new();
+    }
+
+    // The output covers the whole source, so its length is the source length scaled by the ratio, rounded up
+    let output_frames = (source_frames * target_sample_rate).div_ceil(source_sample_rate);
+    let mut output = Vec::with_capacity(output_frames * target_channels);
 
-    while read_pos + 1.0 < source_frames as f32 {
-        // Calculate the index from the read position
-        let index = read_pos.floor() as usize;
-        let remainder = read_pos - read_pos.floor();
+    for frame in 0..output_frames {
+        // Calculate the read position as an integer index and a fraction, so it doesn't drift in long sources
+        let scaled = frame * source_sample_rate;
+        let index = scaled / target_sample_rate;
+        let remainder = ((scaled % target_sample_rate) as f64 / target_sample_rate as f64) as f32;
+        // The last frame has no following frame to interpolate with, so hold it
+        let next_index = (index + 1).min(source_frames - 1);
 
         for target_channel in 0..target_channels {
             // Complement the sample in the index, or push zero
             if target_channel < source_channels {
                 // Get the two samples to interpolate the sample
                 let src_before = source[index * source_channels + target_channel];
-                let src_after = source[(index + 1) * source_channels + target_channel];
+                let src_after = source[next_index * source_channels + target_channel];
                 // LERP
                 output.push(src_before * (1.0 - remainder) + src_after * remainder);
             } else {
@@ -29,9 +36,149 @@ pub fn resample_channels(
                 output.push(0.0);
             }
         }
-
-        read_pos += ratio;
     }
 
     output
 }
+
+/// The smallest ratio accepted by `VariableResampler`, which limits the output to 100 times the input.
+const MIN_RATIO: f64 = 0.01;
+
+/// A streaming resampler whose ratio can change for every chunk, for speed effects such as a tape stop.
+/// The ratio is the number of input frames consumed for each output frame, so 1.0 keeps the speed,
+/// 2.0 plays twice as fast and 0.5 plays at half speed. The ratio ramps linearly from the ratio of the previous
+/// chunk, so automating it doesn't cause steps. Uses linear interpolation and carries the read position
+/// and the last frame across chunks. `resample_channels` stays the path for fixed ratios.
+#[derive(Clone)]
+pub struct VariableResampler {
+    channels: usize,
+    /// The read position relative to the last frame of the previous chunk.
+    position: f64,
+    /// The last frame of the previous chunk, interpolated with the first frame of the next chunk.
+    last_frame: Vec<f32>,
+    /// The ratio at the end of the previous chunk.
+    last_ratio: Option<f64>,
+}
+
+impl VariableResampler {
+    /// Creates a resampler for interleaved audio with the given channels.
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            position: 1.0,
+            last_frame: vec![0.0; channels],
+            last_ratio: None,
+        }
+    }
+
+    /// Clears the read position and the last frame, such as after seeking.
+    pub fn reset(&mut self) {
+        self.position = 1.0;
+        self.last_frame.fill(0.0);
+        self.last_ratio = None;
+    }
+
+    /// Resamples the interleaved chunk, ramping the ratio from the previous ratio to the given ratio.
+    /// Returns the interleaved output, whose length varies with the ratio. The ratio is clamped to at least 0.01.
+    /// The output between the last frame of the chunk and the next chunk is produced with the next chunk.
+    pub fn process_with_ratio(&mut self, input: &[f32], ratio: f64) -> Vec<f32> {
+        let channels = self.channels;
+        let frames = input.len() / channels;
+        let ratio = ratio.max(MIN_RATIO);
+        let start_ratio = self.last_ratio.unwrap_or(ratio);
+        self.last_ratio = Some(ratio);
+        if frames == 0 {
+            return Vec::new();
+        }
+
+        // Frame 0 is the last frame of the previous chunk, and frame i is the frame i - 1 of the input
+        let frame_at = |index: usize, channel: usize| {
+            if index == 0 {
+                self.last_frame[channel]
+            } else {
+                input[(index - 1) * channels + channel]
+            }
+        };
+
+        let mut output =
+            Vec::with_capacity(((frames as f64 / ratio.min(start_ratio)) as usize + 1) * channels);
+        let mut position = self.position;
+        while position < frames as f64 {
+            let index = position.floor() as usize;
+            let remainder = (position - index as f64) as f32;
+            for channel in 0..channels {
+                let before = frame_at(index, channel);
+                let after = frame_at(index + 1, channel);
+                output.push(before + (after - before) * remainder);
+            }
+
+            // Ramp the ratio over the chunk
+            let progress = position / frames as f64;
+            position += start_ratio + (ratio - start_ratio) * progress;
+        }
+
+        // Carry the position and the last frame to the next chunk
+        self.position = position - frames as f64;
+        self.last_frame
+            .copy_from_slice(&input[(frames - 1) * channels..frames * channels]);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_length_is_rounded_up() {
+        let source = vec![0.0; 441 * 2];
+        let output = resample_channels(&source, 441, 44100, 2, 48000, 2);
+        assert_eq!(output.len(), 480 * 2);
+
+        let output = resample_channels(&source, 441, 44100, 2, 22050, 2);
+        assert_eq!(output.len(), 221 * 2);
+    }
+
+    #[test]
+    fn resample_keeps_pitch_in_long_sources() {
+        // A ramp of the frame index, read back at the exact source position of the output frame
+        let source_frames = 1 << 21;
+        let source: Vec<f32> = (0..source_frames).map(|frame| frame as f32).collect();
+        let output = resample_channels(&source, source_frames, 44100, 1, 48000, 1);
+        assert_eq!(output.len(), (source_frames * 48000).div_ceil(44100));
+
+        let frame = output.len() - 2;
+        let expected = frame as f64 * 44100.0 / 48000.0;
+        assert!((output[frame] as f64 - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn resample_reaches_the_end_of_the_source() {
+        let source: Vec<f32> = (0..100).map(|frame| frame as f32).collect();
+        for target_sample_rate in [22050, 44100, 48000, 96000] {
+            let output = resample_channels(&source, 100, 44100, 1, target_sample_rate, 1);
+            let last = *output.last().unwrap();
+            let step = 44100.0 / target_sample_rate as f32;
+            assert!(
+                last > 99.0 - step && last <= 99.0,
+                "{target_sample_rate}: {last}"
+            );
+        }
+    }
+
+    #[test]
+    fn resample_ends_past_the_f32_precision() {
+        // The frame count exceeds the integers an f32 holds exactly
+        let source_frames = (1 << 24) + 1;
+        let source = vec![0.0; source_frames];
+        let output = resample_channels(&source, source_frames, 44100, 1, 48000, 1);
+        assert_eq!(output.len(), (source_frames * 48000).div_ceil(44100));
+    }
+
+    #[test]
+    fn resample_empty_source() {
+        assert!(resample_channels(&[], 0, 44100, 2, 48000, 2).is_empty());
+        assert!(resample_channels(&[0.0; 4], 2, 0, 2, 48000, 2).is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Where the samples of an audio region are stored when the region is saved.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourceStorage {
+    /// The samples are saved inside the region.
+    #[default]
+    Embedded,
+    /// Only the path to the audio file is saved. The host decodes the file and sets the samples with
+    /// `AudioRegion::set_data` after loading, and the region plays silence until then.
+    External(PathBuf),
+}
@@ -1,88 +1,278 @@
-use crate::{
-    data_types::Beats,
-    mixer::TempoMap,
-    track::audio_track::{AudioRegion, resampler::resample_channels},
-};
+use crate::{mixer::TempoMap, track::audio_track::AudioRegion};
 
 /// Strech the audio data using the given tempo map, not preserving the pitch.
+/// Every output frame reads the source at the exact position of its beat, so each region keeps its own
+/// fraction of a sample and stays aligned with the beats across tempo changes, whatever its sample rate.
+/// The output starts at the frame of the region start and ends at the region end, or at the end of the
+/// source if the source doesn't loop. The source channels are mapped to the target channels with `map_channels`,
+/// and the inverted channels of the region are negated.
+/// The regions not locked to the tempo play the source at its own rate from the region start, keeping the pitch.
 pub fn tempo_strech(
     src_region: &AudioRegion,
     target_sample_rate: usize,
     target_channels: usize,
     tempo_map: &TempoMap,
 ) -> Vec<f32> {
-    let region_end = src_region.start + src_region.duration;
+    let channels = src_region.channels as usize;
+    if channels == 0 || src_region.frames == 0 || target_sample_rate == 0 {
+        return Vec::new();
+    }
+
+    let start_frame = tempo_map.beats_to_samples(src_region.start);
+    let end_frame = tempo_map.beats_to_samples(src_region.get_end());
+    // The number of source frames played in a beat at the base BPM
+    let frames_per_beat = src_region.sample_rate as f64 * 60.0 / src_region.base_bpm;
+    let (loop_start, loop_frames) = src_region.get_loop_frames();
+    let is_looping = src_region.loop_source && loop_frames > 0;
+    // The exact frame of the region start, from which the regions not locked to the tempo play at the source rate
+    let exact_start_frame =
+        tempo_map.beats_to_seconds(src_region.start) * target_sample_rate as f64;
+    let source_rate = src_region.sample_rate as f64 / target_sample_rate as f64;
 
-    // Create a section list to split the data by tempo changes
-    // Get the first event on or before the region start beat
-    let start_index = tempo_map
+    // The source channels mixed into each target channel, with the gain and the polarity of each channel
+    let channel_map: Vec<Vec<(usize, f32)>> = map_channels(channels, target_channels)
+        .into_iter()
+        .map(|sources| {
+            sources
+                .into_iter()
+                .map(|(source, gain)| {
+                    let sign = if src_region.is_channel_inverted(source) {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    (source, gain * sign)
+                })
+                .collect()
+        })
+        .collect();
+
+    // Returns the source frame read at the given frame index, wrapping the frames past the end when looping
+    let source_frame = |frame: usize| {
+        if frame < src_region.frames {
+            Some(frame)
+        } else if is_looping {
+            Some(loop_start + (frame - loop_start) % loop_frames)
+        } else {
+            None
+        }
+    };
+
+    // Get the tempo event on or before the region start
+    let mut event_index = tempo_map
         .events
-        .partition_point(|e| e.beat <= src_region.start)
+        .partition_point(|e| e.sample_offset <= start_frame)
         .saturating_sub(1);
-    // Loop over the events until it surpasses the region end beat
-    // (0: Start beat, 1: End beat, 2: BPM of the section)
-    let mut sections: Vec<(Beats, Beats, f64)> = Vec::new();
-    let mut i = start_index;
-    while let Some(event) = tempo_map.events.get(i) {
-        // Break if the event beat surpasses the region end beat
-        if event.beat >= region_end {
-            break;
+
+    let mut output_data =
+        Vec::with_capacity(end_frame.saturating_sub(start_frame) * target_channels);
+    for frame in start_frame..end_frame {
+        // Move to the tempo event the frame belongs to
+        while tempo_map
+            .events
+            .get(event_index + 1)
+            .is_some_and(|next| next.sample_offset <= frame)
+        {
+            event_index += 1;
         }
+        let Some(event) = tempo_map.events.get(event_index) else {
+            break;
+        };
 
-        // Get the start and the end beat of the section
-        let section_start = if i == start_index {
-            src_region.start
+        // Calculate the exact position in the source from the beat of the frame
+        // The region starts playing the source from the source offset
+        let position = if src_region.tempo_locked {
+            let elapsed_seconds =
+                frame.saturating_sub(event.sample_offset) as f64 / target_sample_rate as f64;
+            let beat = event.beat.0 + elapsed_seconds * event.bpm / 60.0;
+            (beat - src_region.start.0 + src_region.source_offset.0) * frames_per_beat
         } else {
-            event.beat
+            (frame as f64 - exact_start_frame) * source_rate
+                + src_region.source_offset.0 * frames_per_beat
+        }
+        .max(0.0);
+        let index = position.floor() as usize;
+        let remainder = (position - position.floor()) as f32;
+
+        // Stop at the end of the source unless it loops
+        let Some(before) = source_frame(index) else {
+            break;
         };
-        let section_end = tempo_map
-            .events
-            .get(i + 1)
-            .map(|next| next.beat.min(region_end))
-            .unwrap_or(region_end);
+        // The frames past the edges of the source are held at the edges
+        let after = source_frame(index + 1).unwrap_or(before);
+        let previous = index
+            .checked_sub(1)
+            .and_then(source_frame)
+            .unwrap_or(before);
+        let next = source_frame(index + 2).unwrap_or(after);
+
+        for sources in &channel_map {
+            // Channels without a source stay silent
+            let mut mixed = 0.0;
+            for (source, gain) in sources {
+                let sample = |frame: usize| src_region.data[frame * channels + source];
+                mixed += gain
+                    * src_region.interpolation.interpolate(
+                        sample(previous),
+                        sample(before),
+                        sample(after),
+                        sample(next),
+                        remainder,
+                    );
+            }
+            output_data.push(mixed);
+        }
+    }
+
+    output_data
+}
 
-        // Push the section
-        sections.push((section_start, section_end, event.bpm));
+/// The gain of the center and the surround channels folded into the front channels, which is -3 dB.
+const FOLD_DOWN_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
 
-        i += 1;
+/// Returns the source channels mixed into each target channel with their gains.
+/// - A mono source plays on every target channel at full level.
+/// - A 5.1 source (L, R, C, LFE, Ls, Rs) folded to stereo mixes the center and the surround channels into the front
+///   channels at -3 dB and drops the LFE, normalized so that the channels at full level don't clip.
+/// - Any other source with more channels than the target is folded down by averaging every source channel into
+///   the target channel of its index modulo the target channels, so correlated channels keep their level.
+/// - Otherwise each source channel plays on the target channel of the same index, leaving the extra target channels silent.
+pub(super) fn map_channels(
+    source_channels: usize,
+    target_channels: usize,
+) -> Vec<Vec<(usize, f32)>> {
+    if source_channels == 6 && target_channels == 2 {
+        let gain = 1.0 / (1.0 + 2.0 * FOLD_DOWN_GAIN);
+        let fold = |front: usize, surround: usize| {
+            vec![
+                (front, gain),
+                (2, FOLD_DOWN_GAIN * gain),
+                (surround, FOLD_DOWN_GAIN * gain),
+            ]
+        };
+        return vec![fold(0, 4), fold(1, 5)];
     }
 
-    // Loop over the sections and resample the audio
-    let mut output_data = Vec::new();
-    for section in sections {
-        // Calculate the start and the end index in the region data to get the slice
-        let src_start_beats = section.0 - src_region.start;
-        let src_end_beats = section.1 - src_region.start;
-        let src_start_sample = (src_start_beats.0 / src_region.base_bpm
-            * 60.0
-            * src_region.sample_rate as f64) as usize;
-        let src_end_sample = ((src_end_beats.0 / src_region.base_bpm
-            * 60.0
-            * src_region.sample_rate as f64) as usize)
-            .min(src_region.frames);
-
-        let src_start_index = src_start_sample * src_region.channels as usize;
-        let src_end_index = src_end_sample * src_region.channels as usize;
-
-        // Get the slice from the data
-        let section_data = &src_region.data[src_start_index..src_end_index];
-        let section_frames = src_end_sample - src_start_sample;
-
-        // Calculate the source sample rate to change the tempo
-        let src_sample_rate =
-            (src_region.sample_rate as f64 * (src_region.base_bpm / section.2)) as usize;
-        let resampled_data = resample_channels(
-            section_data,
-            section_frames,
-            src_sample_rate,
-            src_region.channels as usize,
-            target_sample_rate,
-            target_channels,
-        );
-
-        // Append the resampled audio to the output data
-        output_data.extend(resampled_data);
+    (0..target_channels)
+        .map(|target| {
+            if source_channels == 1 {
+                return vec![(0, 1.0)];
+            }
+            let sources: Vec<usize> = (target..source_channels).step_by(target_channels).collect();
+            let gain = 1.0 / sources.len().max(1) as f32;
+            sources.into_iter().map(|source| (source, gain)).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_types::{AudioContext, Beats};
+
+    fn tempo_map() -> TempoMap {
+        let audio_ctx = AudioContext {
+            channels: 2,
+            sample_rate: 48000,
+            buffer_size: 64,
+            max_voices: 1,
+        };
+        TempoMap::new(audio_ctx, 120.0)
     }
 
-    output_data
+    #[test]
+    fn mono_plays_on_both_stereo_channels() {
+        assert_eq!(map_channels(1, 2), [vec![(0, 1.0)], vec![(0, 1.0)]]);
+    }
+
+    #[test]
+    fn stereo_is_averaged_to_mono() {
+        assert_eq!(map_channels(2, 1), [vec![(0, 0.5), (1, 0.5)]]);
+    }
+
+    #[test]
+    fn surround_is_folded_to_stereo() {
+        let map = map_channels(6, 2);
+        let sources = |target: usize| map[target].iter().map(|(s, _)| *s).collect::<Vec<_>>();
+        assert_eq!(sources(0), [0, 2, 4]);
+        assert_eq!(sources(1), [1, 2, 5]);
+
+        // The front channel is 3 dB louder than the center and the surround, and full level input doesn't clip
+        for sources in &map {
+            let gains: Vec<f32> = sources.iter().map(|(_, gain)| *gain).collect();
+            assert!((gains[1] / gains[0] - FOLD_DOWN_GAIN).abs() < 1e-6);
+            assert_eq!(gains[1], gains[2]);
+            assert!((gains.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn matching_channels_map_one_to_one() {
+        assert_eq!(map_channels(2, 2), [vec![(0, 1.0)], vec![(1, 1.0)]]);
+        assert_eq!(map_channels(2, 3), [vec![(0, 1.0)], vec![(1, 1.0)], vec![]]);
+    }
+
+    #[test]
+    fn mono_region_plays_on_both_channels_of_a_stereo_track() {
+        let source: Vec<f32> = (0..100).map(|frame| frame as f32 / 100.0).collect();
+        let mut region =
+            AudioRegion::from_planar(std::slice::from_ref(&source), 48000, 120.0, Beats(0.0));
+        region.declick = false;
+
+        let output = tempo_strech(&region, 48000, 2, &tempo_map());
+        assert_eq!(output.len(), source.len() * 2);
+        for (frame, sample) in output.chunks_exact(2).zip(&source) {
+            assert_eq!(frame, [*sample, *sample]);
+        }
+    }
+
+    /// Returns a mono region whose samples are their time in seconds, lasting one second at the sample rate.
+    fn ramp_region(sample_rate: u32, start: Beats) -> AudioRegion {
+        let source = (0..sample_rate)
+            .map(|frame| frame as f32 / sample_rate as f32)
+            .collect();
+        AudioRegion::from_planar(&[source], sample_rate, 120.0, start)
+    }
+
+    #[test]
+    fn output_is_the_source_at_the_base_tempo() {
+        let left: Vec<f32> = (0..480).map(|frame| (frame as f32 * 0.1).sin()).collect();
+        let right: Vec<f32> = left.iter().map(|sample| -sample).collect();
+        let region = AudioRegion::from_planar(&[left, right], 48000, 120.0, Beats(0.0));
+
+        let output = tempo_strech(&region, 48000, 2, &tempo_map());
+        assert_eq!(output, *region.data);
+    }
+
+    #[test]
+    fn output_lasts_the_region_at_the_tempo() {
+        // One second of the source is two beats, which last two seconds at 60 BPM
+        let region = ramp_region(48000, Beats(0.0));
+        let mut tempo_map = tempo_map();
+        tempo_map.change_bpm(0, 60.0);
+        assert_eq!(tempo_strech(&region, 48000, 1, &tempo_map).len(), 96000);
+
+        // A region starting later ends at the same length
+        let region = ramp_region(48000, Beats(1.5));
+        assert_eq!(tempo_strech(&region, 48000, 1, &tempo_map).len(), 96000);
+    }
+
+    #[test]
+    fn regions_at_different_rates_stay_aligned() {
+        let tempo_map = tempo_map();
+        for start in [Beats(0.0), Beats(0.3)] {
+            let outputs = [44100, 48000, 96000].map(|sample_rate| {
+                tempo_strech(&ramp_region(sample_rate, start), 48000, 1, &tempo_map)
+            });
+
+            // Every region reads the same time of its source at every frame, whatever its rate
+            for output in &outputs {
+                assert!(output.len().abs_diff(48000) <= 1);
+                for (frame, sample) in output.iter().enumerate().take(47900) {
+                    let time = frame as f32 / 48000.0;
+                    assert!((sample - time).abs() < 1e-4, "{} at {}", sample, time);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,196 @@
+use crate::{
+    audio_utils::mix_into,
+    data_types::{AudioContext, Beats},
+    graph::{Graph, error::GraphError},
+    mixer::TempoMap,
+    node::builtin::{AudioInputNode, AudioOutputNode},
+    track::{CollisionPolicy, RegionID, Track},
+};
+
+/// A track without regions, which processes the audio sent from other tracks.
+#[derive(Clone)]
+pub struct BusTrack {
+    // --- GRAPH ---
+    graph: Graph,
+
+    // --- INPUT ---
+    /// The sum of the audio sent to the bus in the current buffer.
+    input: Vec<f32>,
+    /// The gain applied to the sent audio before the graph.
+    input_gain: f32,
+
+    // --- AUDIO CONTEXT ---
+    audio_ctx: AudioContext,
+}
+
+impl Default for BusTrack {
+    fn default() -> Self {
+        Self {
+            graph: Graph::default(),
+            input: Vec::new(),
+            // The sent audio passes at unity gain unless set otherwise
+            input_gain: 1.0,
+            audio_ctx: AudioContext::default(),
+        }
+    }
+}
+
+impl BusTrack {
+    pub fn new(audio_ctx: AudioContext) -> Self {
+        // Create a graph with the input and output nodes
+        let input_node = AudioInputNode::default();
+        let output_node = AudioOutputNode::default();
+        let mut graph = Graph::new(
+            Box::new(input_node),
+            Box::new(output_node),
+            audio_ctx.clone(),
+        );
+        // Connect the input to the output so the sent audio passes through an empty graph
+        graph.add_edge_unchecked((graph.get_input_id(), 0, graph.get_output_id(), 0));
+
+        Self {
+            graph,
+            input: vec![0.0; audio_ctx.buffer_size * audio_ctx.channels],
+            input_gain: 1.0,
+            audio_ctx,
+        }
+    }
+
+    // --- INPUT ---
+
+    /// Adds the given audio to the bus input, scaled by the level and the input gain.
+    /// Must be called before process() in the same buffer.
+    pub fn pass_audio(&mut self, samples: &[f32], level: f32) {
+        mix_into(&mut self.input, samples, level * self.input_gain);
+    }
+}
+
+impl Track for BusTrack {
+    // --- CLONING ---
+
+    fn clone_box(&self) -> Box<dyn Track> {
+        Box::new(self.clone())
+    }
+
+    // --- GRAPH GETTING ---
+
+    fn get_graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    fn get_graph_mut(&mut self) -> &mut Graph {
+        &mut self.graph
+    }
+
+    // --- GRAPH UPDATING ---
+
+    fn set_graph(&mut self, graph: Graph) {
+        self.graph = graph;
+    }
+
+    // --- AUDIO CONTEXT UPDARING ---
+
+    fn set_audio_ctx(&mut self, audio_ctx: &AudioContext) {
+        self.audio_ctx = audio_ctx.clone();
+        self.input = vec![0.0; audio_ctx.buffer_size * audio_ctx.channels];
+        self.graph.set_audio_ctx(audio_ctx);
+    }
+
+    // Bus tracks have no regions to follow the tempo
+    fn on_tempo_changed(&mut self, _old_tempo_map: &TempoMap, _new_tempo_map: &TempoMap) {}
+
+    // --- REGION MODIFICATION ---
+
+    // Bus tracks have no regions
+
+    fn move_region(&mut self, _region_id: &RegionID, _new_start: Beats) {}
+
+    fn set_input_gain(&mut self, gain: f32) {
+        self.input_gain = gain;
+    }
+
+    fn get_input_gain(&self) -> f32 {
+        self.input_gain
+    }
+
+    fn set_collision_policy(&mut self, _policy: CollisionPolicy) {}
+
+    fn get_collision_policy(&self) -> CollisionPolicy {
+        CollisionPolicy::Overlap
+    }
+
+    fn set_region_duration(&mut self, _region_id: &RegionID, _new_duration: Beats) {}
+
+    fn remove_region(&mut self, _region_id: &RegionID) {}
+
+    fn get_regions_at(&self, _at: Beats) -> Vec<RegionID> {
+        Vec::new()
+    }
+
+    fn get_regions_in_range(&self, _start: Beats, _end: Beats) -> Vec<RegionID> {
+        Vec::new()
+    }
+
+    fn split_region(&mut self, _region_id: &RegionID, _at: Beats) -> Option<(RegionID, RegionID)> {
+        None
+    }
+
+    fn merge_regions(&mut self, _first: &RegionID, _second: &RegionID) -> bool {
+        false
+    }
+
+    // --- FREEZING ---
+
+    // Buses process the audio sent in each buffer, so they can't be frozen
+    fn freeze(&mut self) {}
+
+    fn unfreeze(&mut self) {}
+
+    fn is_frozen(&self) -> bool {
+        false
+    }
+
+    // --- SEEKING ---
+
+    fn seek(&mut self, _playhead: usize) {
+        self.input.fill(0.0);
+    }
+
+    // --- TRACK PROCESSING ---
+
+    fn prepare(
+        &mut self,
+        _start: usize,
+        _duration: usize,
+        _tempo_map: &TempoMap,
+    ) -> Result<(), GraphError> {
+        self.input = vec![0.0; self.audio_ctx.buffer_size * self.audio_ctx.channels];
+        self.graph.prepare()
+    }
+
+    fn process(
+        &mut self,
+        _is_playing: bool,
+        _playhead: usize,
+        output: &mut [f32],
+    ) -> Result<(), GraphError> {
+        // Process the bus even while paused so the tails of live input keep ringing
+        let input_ptr = self.input.as_ptr() as *const u8;
+        let result = self
+            .graph
+            .process(&[input_ptr], &[output.as_mut_ptr() as *mut u8]);
+        // Clear the input for the next buffer
+        self.input.fill(0.0);
+        result
+    }
+
+    // --- ANY CASTING ---
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
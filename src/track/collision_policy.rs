@@ -0,0 +1,38 @@
+use crate::data_types::Beats;
+use serde::{Deserialize, Serialize};
+
+/// How a track resolves a region overlapping other regions after it is added or moved.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollisionPolicy {
+    /// Leaves the regions overlapped, so their audio is summed.
+    #[default]
+    Overlap,
+    /// Fades the overlapping parts of the regions into each other.
+    /// A region inside another region overlaps it, as there's no room for the fades.
+    /// Tracks without fades, such as note tracks, overlap the regions instead.
+    Crossfade,
+    /// Moves the following regions later, so they start after the end of the region.
+    /// The regions pushed away push the regions after them in turn.
+    Push,
+}
+
+/// Moves the regions starting within or after the moved region so that no region overlaps the one before it.
+/// Each item of `others` is the start and the duration of a region other than the moved one.
+pub(crate) fn push_regions<'a>(
+    moved_start: Beats,
+    moved_end: Beats,
+    others: impl Iterator<Item = (&'a mut Beats, Beats)>,
+) {
+    let mut following: Vec<(&mut Beats, Beats)> =
+        others.filter(|(start, _)| **start >= moved_start).collect();
+    following.sort_by_key(|(start, _)| **start);
+
+    // Push the regions in order, carrying the end of the last region forward
+    let mut end = moved_end;
+    for (start, duration) in following {
+        if *start < end {
+            *start = end;
+        }
+        end = end.max(*start + duration);
+    }
+}
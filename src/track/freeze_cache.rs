@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+/// Holds the rendered output of a frozen track.
+#[derive(Default, Clone)]
+pub(crate) struct FreezeCache {
+    is_frozen: bool,
+    /// Interleaved output of the track from the beginning, shared between the clones of the track.
+    rendered: Option<Arc<Vec<f32>>>,
+}
+
+impl FreezeCache {
+    /// Marks the track as frozen. The output is rendered in the next prepare.
+    pub fn freeze(&mut self) {
+        self.is_frozen = true;
+    }
+
+    /// Marks the track as not frozen and drops the rendered output.
+    pub fn unfreeze(&mut self) {
+        self.is_frozen = false;
+        self.rendered = None;
+    }
+
+    /// Returns whether the track is frozen.
+    pub fn is_frozen(&self) -> bool {
+        self.is_frozen
+    }
+
+    /// Drops the rendered output, so it is rendered again in the next prepare.
+    pub fn invalidate(&mut self) {
+        self.rendered = None;
+    }
+
+    /// Returns whether the track is frozen but the output has not been rendered.
+    pub fn needs_render(&self) -> bool {
+        self.is_frozen && self.rendered.is_none()
+    }
+
+    /// Returns the length of the rendered output in samples, or None if it has not been rendered.
+    pub fn rendered_len(&self) -> Option<usize> {
+        self.rendered.as_ref().map(|rendered| rendered.len())
+    }
+
+    /// Stores the rendered output.
+    pub fn set_rendered(&mut self, rendered: Vec<f32>) {
+        self.rendered = Some(Arc::new(rendered));
+    }
+
+    /// Copies the rendered output from the sample index into the output, padding with zeros after the end.
+    /// Returns false if the output has not been rendered.
+    pub fn read(&self, index: usize, output: &mut [f32]) -> bool {
+        let Some(rendered) = self.rendered.as_ref().filter(|_| self.is_frozen) else {
+            return false;
+        };
+        let start = index.min(rendered.len());
+        let available = (rendered.len() - start).min(output.len());
+        output[..available].copy_from_slice(&rendered[start..start + available]);
+        output[available..].fill(0.0);
+        true
+    }
+}
@@ -1,9 +1,18 @@
 pub mod audio_track;
+pub mod bus_track;
+mod collision_policy;
+mod freeze_cache;
 pub mod note_track;
 mod region_id;
+mod saved_track;
 
+pub use collision_policy::CollisionPolicy;
 pub use region_id::RegionID;
 
+pub(crate) use collision_policy::push_regions;
+pub(crate) use freeze_cache::FreezeCache;
+pub(crate) use saved_track::SavedTrack;
+
 use crate::{
     data_types::{AudioContext, Beats},
     graph::{Graph, error::GraphError},
@@ -25,17 +34,62 @@ pub trait Track: Send + Any {
     fn set_graph(&mut self, graph: Graph);
 
     /// Moves the audio region to the new start beats.
+    /// Overlaps with other regions are resolved by the collision policy of the track.
     fn move_region(&mut self, region_id: &RegionID, new_start: Beats);
 
-    /// Changes the duration of the region.
+    /// Sets the gain applied to the input of the graph, before any node processes it.
+    /// Like the trim knob of a channel strip, it drives the nodes without changing the output level. Defaults to 1.0.
+    /// Note tracks have no audio input, so they ignore it.
+    fn set_input_gain(&mut self, gain: f32);
+
+    /// Returns the gain applied to the input of the graph.
+    fn get_input_gain(&self) -> f32;
+
+    /// Sets how the track resolves overlapping regions when a region is added or moved.
+    /// Defaults to `CollisionPolicy::Overlap`, which leaves the regions as they are.
+    fn set_collision_policy(&mut self, policy: CollisionPolicy);
+
+    /// Returns how the track resolves overlapping regions.
+    fn get_collision_policy(&self) -> CollisionPolicy;
+
+    /// Changes the duration of the region, resolving overlaps with the collision policy.
     fn set_region_duration(&mut self, region_id: &RegionID, new_duration: Beats);
 
     /// Removes the region from the track.
     fn remove_region(&mut self, region_id: &RegionID);
 
+    /// Returns the IDs of the regions playing at the given beats, sorted by the ID.
+    /// A region plays from its start up to, but not including, its end.
+    fn get_regions_at(&self, at: Beats) -> Vec<RegionID>;
+
+    /// Returns the IDs of the regions overlapping the range between the given beats, sorted by the ID.
+    /// Regions which only touch the edges of the range are not included.
+    fn get_regions_in_range(&self, start: Beats, end: Beats) -> Vec<RegionID>;
+
+    /// Splits the region into two at the given beats, and returns the IDs of the former and the latter region.
+    /// The former region keeps the original ID. Returns None if the region is not found or the beats is outside the region.
+    fn split_region(&mut self, region_id: &RegionID, at: Beats) -> Option<(RegionID, RegionID)>;
+
+    /// Merges the second region into the first region if the second one directly follows the first one.
+    /// Returns whether the regions were merged.
+    fn merge_regions(&mut self, first: &RegionID, second: &RegionID) -> bool;
+
+    /// Freezes the track, so it plays back its output rendered in `prepare` instead of processing the graph.
+    /// The output is rendered again in the next `prepare` after the regions or the graph change.
+    fn freeze(&mut self);
+
+    /// Unfreezes the track and drops the rendered output. The track must be prepared again to reset the node state.
+    fn unfreeze(&mut self);
+
+    /// Returns whether the track is frozen.
+    fn is_frozen(&self) -> bool;
+
     /// Sets the audio context to the new one.
     fn set_audio_ctx(&mut self, audio_ctx: &AudioContext);
 
+    /// Called when the tempo map of the project changes, before the track is prepared with the new tempo map.
+    fn on_tempo_changed(&mut self, old_tempo_map: &TempoMap, new_tempo_map: &TempoMap);
+
     /// Prepares for the seeking.
     fn seek(&mut self, playhead: usize);
 
@@ -48,7 +102,12 @@ pub trait Track: Send + Any {
     ) -> Result<(), GraphError>;
 
     /// Processes the track with the given input and output pointer.
-    fn process(&mut self, is_playing: bool, playhead: usize, output: &mut [f32]);
+    fn process(
+        &mut self,
+        is_playing: bool,
+        playhead: usize,
+        output: &mut [f32],
+    ) -> Result<(), GraphError>;
 
     /// Converts a reference to the track to any.
     fn as_any(&self) -> &dyn Any;
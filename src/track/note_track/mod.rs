@@ -10,7 +10,7 @@ use crate::{
     graph::{Graph, error::GraphError},
     mixer::TempoMap,
     node::builtin::{AudioOutputNode, NoteInputNode},
-    track::{RegionID, Track},
+    track::{CollisionPolicy, FreezeCache, RegionID, Track, push_regions},
 };
 use std::collections::{HashMap, VecDeque};
 use voice_event::VoiceEvent;
@@ -33,6 +33,12 @@ pub struct NoteTrack {
     // Live MIDI voices: MIDI note number -> voice index
     live_voices: HashMap<u8, usize>,
 
+    // --- FREEZING ---
+    freeze_cache: FreezeCache,
+
+    // --- EDITING ---
+    collision_policy: CollisionPolicy,
+
     // --- AUDIO CONTEXT ---
     audio_ctx: AudioContext,
 
@@ -78,15 +84,22 @@ impl NoteTrack {
         self.next_region_id = next_id;
     }
 
+    /// Returns the ID which will be given to the next added region.
+    pub fn get_next_region_id(&self) -> usize {
+        self.next_region_id
+    }
+
     fn generate_region_id(&mut self) -> RegionID {
         let id = RegionID(self.next_region_id);
         self.next_region_id += 1;
         id
     }
 
+    /// Adds the region to the track, resolving overlaps with the collision policy, and returns the ID of the region.
     pub fn add_region(&mut self, region: NoteRegion) -> RegionID {
         let id = self.generate_region_id();
         self.regions.insert(id, region);
+        self.resolve_collisions(&id);
         id
     }
 
@@ -94,6 +107,28 @@ impl NoteTrack {
         self.regions = regions;
     }
 
+    // --- COLLISION RESOLVING ---
+
+    /// Resolves the overlaps between the region and the other regions with the collision policy.
+    /// Note regions have no fades, so the crossfade policy leaves them overlapped.
+    fn resolve_collisions(&mut self, id: &RegionID) {
+        let Some(region) = self.regions.get(id) else {
+            return;
+        };
+        let (start, end) = (region.start, region.start + region.duration);
+
+        if self.collision_policy == CollisionPolicy::Push {
+            push_regions(
+                start,
+                end,
+                self.regions
+                    .iter_mut()
+                    .filter(|(other_id, _)| *other_id != id)
+                    .map(|(_, other)| (&mut other.start, other.duration)),
+            );
+        }
+    }
+
     // --- VOICE GETTING ---
 
     /// Returns the vacant voice index, or returns the index of the oldest voice.
@@ -138,6 +173,129 @@ impl NoteTrack {
             }
         }
     }
+
+    // --- RENDERING ---
+
+    /// Updates the voices for the buffer at the playhead and processes the graph.
+    fn render(
+        &mut self,
+        is_playing: bool,
+        playhead: usize,
+        output: &mut [f32],
+    ) -> Result<(), GraphError> {
+        // Convert the playhead beats to samples
+        let buffer_end = playhead + self.audio_ctx.buffer_size;
+        let max_voices = self.audio_ctx.max_voices;
+
+        // Seek the event cursor
+        if self
+            .events
+            .get(self.event_cursor)
+            .is_some_and(|e| e.sample_index > playhead)
+            || (self.event_cursor > 0 && self.events[self.event_cursor - 1].sample_index > playhead)
+        {
+            self.event_cursor = self.events.partition_point(|e| e.sample_index < playhead);
+        }
+
+        for sample in playhead..buffer_end {
+            // Calculate the local sample in the buffer chunk
+            let local_sample = sample - playhead;
+            // Calculate the index of the first current voice
+            let current = local_sample * max_voices;
+
+            // If the current sample is the first sample in the buffer,
+            // Copy from the last voices
+            if local_sample == 0 && !self.last_voices.is_empty() {
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        self.last_voices.as_ptr(),
+                        self.voice_buffer.as_mut_ptr(),
+                        max_voices,
+                    );
+                }
+            }
+
+            // If the current sample is not the first sample in the buffer,
+            // copy the previous voices to the current index
+            if local_sample > 0 {
+                let previous = (local_sample - 1) * max_voices;
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        self.voice_buffer[previous..].as_ptr(),
+                        self.voice_buffer[current..].as_mut_ptr(),
+                        max_voices,
+                    );
+                }
+            }
+
+            // Increment age for live midi voices
+            for &index in self.live_voices.values() {
+                self.voice_buffer[current + index].age += 1.0 / self.audio_ctx.sample_rate as f32;
+            }
+
+            // Process the sequenced voices when playing
+            if is_playing {
+                // Increment age for sequenced voices
+                for (index, _) in self.active_voices.iter() {
+                    self.voice_buffer[current + index].age +=
+                        1.0 / self.audio_ctx.sample_rate as f32;
+                }
+
+                // Consume the events in this sample
+                while let Some(event) = self.events.get(self.event_cursor) {
+                    // Break if the event is in future
+                    if event.sample_index > sample {
+                        break;
+                    }
+                    // If the event is the past event, skip the event
+                    if event.sample_index < sample {
+                        self.event_cursor += 1;
+                        continue;
+                    }
+
+                    // Copy the frequency and velocity to avoid reference issues
+                    let frequency = event.frequency;
+                    let velocity = event.velocity;
+
+                    if event.is_note_on {
+                        // Start playing the note from the sample
+                        let voice_index = self.find_or_steal_voice(frequency);
+                        // Set the new voice to the voice buffer
+                        self.voice_buffer[current + voice_index] =
+                            Voice::new(frequency, velocity, 0.0, true);
+                    } else {
+                        // Remove the active voice whose frequency matches the event frequency
+                        if let Some(remove_index) = self
+                            .active_voices
+                            .iter()
+                            .position(|(_, freq)| *freq == event.frequency)
+                        {
+                            // Remove the index from the active_voices and get the voice index
+                            let (voice_index, _) = self.active_voices.remove(remove_index).unwrap();
+                            // Mark the voice index as free
+                            self.free_voices.push(voice_index);
+                            self.voice_buffer[current + voice_index].is_active = false;
+                            self.voice_buffer[current + voice_index].age = 0.0;
+                        }
+                    }
+
+                    // Increment the event cursor
+                    self.event_cursor += 1;
+                }
+            }
+        }
+
+        // Copy the last voices
+        let last = (self.audio_ctx.buffer_size - 1) * max_voices;
+        self.last_voices
+            .clone_from_slice(&self.voice_buffer[last..last + max_voices]);
+
+        // Get a pointer to the voice buffer
+        let input_ptr = self.voice_buffer.as_ptr() as *const u8;
+        // Process the graph
+        self.graph
+            .process(&[input_ptr], &[output.as_mut_ptr() as *mut u8])
+    }
 }
 
 impl Track for NoteTrack {
@@ -154,12 +312,15 @@ impl Track for NoteTrack {
     }
 
     fn get_graph_mut(&mut self) -> &mut Graph {
+        // The graph may be modified, so the frozen output must be rendered again
+        self.freeze_cache.invalidate();
         &mut self.graph
     }
 
     // --- GRAPH UPDATING ---
 
     fn set_graph(&mut self, graph: Graph) {
+        self.freeze_cache.invalidate();
         self.graph = graph;
     }
 
@@ -169,25 +330,114 @@ impl Track for NoteTrack {
         if let Some(region) = self.regions.get_mut(region_id) {
             region.start = new_start;
         }
+        self.resolve_collisions(region_id);
+    }
+
+    fn set_input_gain(&mut self, _gain: f32) {
+        // The graph receives notes instead of audio, so there is nothing to scale
+    }
+
+    fn get_input_gain(&self) -> f32 {
+        1.0
+    }
+
+    fn set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+    }
+
+    fn get_collision_policy(&self) -> CollisionPolicy {
+        self.collision_policy
     }
 
     fn set_region_duration(&mut self, region_id: &RegionID, new_duration: Beats) {
         if let Some(region) = self.regions.get_mut(region_id) {
             region.duration = new_duration;
         }
+        self.resolve_collisions(region_id);
     }
 
     fn remove_region(&mut self, region_id: &RegionID) {
         self.regions.remove(region_id);
     }
 
+    fn get_regions_at(&self, at: Beats) -> Vec<RegionID> {
+        let mut ids: Vec<RegionID> = self
+            .regions
+            .iter()
+            .filter(|(_, region)| region.is_active_at(at))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    fn get_regions_in_range(&self, start: Beats, end: Beats) -> Vec<RegionID> {
+        let mut ids: Vec<RegionID> = self
+            .regions
+            .iter()
+            .filter(|(_, region)| region.is_active_between(start, end))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    fn split_region(&mut self, region_id: &RegionID, at: Beats) -> Option<(RegionID, RegionID)> {
+        let latter = self.regions.get_mut(region_id)?.split_at(at)?;
+        let latter_id = self.add_region(latter);
+        Some((*region_id, latter_id))
+    }
+
+    fn merge_regions(&mut self, first: &RegionID, second: &RegionID) -> bool {
+        if first == second {
+            return false;
+        }
+        let (Some(first_region), Some(second_region)) =
+            (self.regions.get(first), self.regions.get(second))
+        else {
+            return false;
+        };
+        if !first_region.can_merge(second_region) {
+            return false;
+        }
+
+        // Extend the first region over the second region
+        let Some(second_region) = self.regions.remove(second) else {
+            return false;
+        };
+        if let Some(first_region) = self.regions.get_mut(first) {
+            first_region.merge(&second_region);
+        }
+        true
+    }
+
     // --- AUDIO CONTEXT UPDARING ---
 
     fn set_audio_ctx(&mut self, audio_ctx: &AudioContext) {
+        self.freeze_cache.invalidate();
         self.audio_ctx = audio_ctx.clone();
         self.graph.set_audio_ctx(audio_ctx);
     }
 
+    // The notes are placed in beats, so they follow the new tempo when the track is prepared
+    fn on_tempo_changed(&mut self, _old_tempo_map: &TempoMap, _new_tempo_map: &TempoMap) {
+        self.freeze_cache.invalidate();
+    }
+
+    // --- FREEZING ---
+
+    fn freeze(&mut self) {
+        self.freeze_cache.freeze();
+    }
+
+    fn unfreeze(&mut self) {
+        self.freeze_cache.unfreeze();
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.freeze_cache.is_frozen()
+    }
+
     // --- SEEKING ---
 
     fn seek(&mut self, playhead: usize) {
@@ -204,16 +454,16 @@ impl Track for NoteTrack {
 
     fn prepare(
         &mut self,
-        _start: usize,
-        _duration: usize,
+        start: usize,
+        duration: usize,
         tempo_map: &TempoMap,
     ) -> Result<(), GraphError> {
-        // Clear the old events
-        self.events.clear();
+        // Keep the old events to check whether the frozen output is outdated
+        let old_events = std::mem::take(&mut self.events);
 
         // Retrieve the notes from the regions in the track
         for region in self.regions.values() {
-            let region_end = region.start + region.duration;
+            let region_end = region.get_end();
 
             // Calculate the start sample of the region
             for note in region.notes.values() {
@@ -262,127 +512,60 @@ impl Track for NoteTrack {
             vec![Voice::default(); self.audio_ctx.buffer_size * self.audio_ctx.max_voices];
 
         // Initialize the voices
+        // Reserve the voice lists so allocating the voices while processing never reallocates them
         self.active_voices.clear();
-        self.free_voices = (0..self.audio_ctx.max_voices).collect();
+        self.active_voices.reserve(self.audio_ctx.max_voices);
+        self.free_voices = Vec::with_capacity(self.audio_ctx.max_voices * 2);
+        self.free_voices.extend(0..self.audio_ctx.max_voices);
         self.last_voices = vec![Voice::default(); self.audio_ctx.max_voices];
+        // A live voice can be held for every MIDI note number
+        self.live_voices.reserve(128);
 
         // Prepare the graph
-        self.graph.prepare()
-    }
-
-    fn process(&mut self, is_playing: bool, playhead: usize, output: &mut [f32]) {
-        // Convert the playhead beats to samples
-        let buffer_end = playhead + self.audio_ctx.buffer_size;
-        let max_voices = self.audio_ctx.max_voices;
-
-        // Seek the event cursor
-        if self
-            .events
-            .get(self.event_cursor)
-            .is_some_and(|e| e.sample_index > playhead)
-            || (self.event_cursor > 0 && self.events[self.event_cursor - 1].sample_index > playhead)
-        {
-            self.event_cursor = self.events.partition_point(|e| e.sample_index < playhead);
+        self.graph.prepare()?;
+
+        // The frozen output is outdated if the notes or the range changed
+        let buffer_size = self.audio_ctx.buffer_size;
+        let total_frames = (start + duration).div_ceil(buffer_size) * buffer_size;
+        let rendered_len = total_frames * self.audio_ctx.channels;
+        if self.events != old_events || self.freeze_cache.rendered_len() != Some(rendered_len) {
+            self.freeze_cache.invalidate();
         }
 
-        for sample in playhead..buffer_end {
-            // Calculate the local sample in the buffer chunk
-            let local_sample = sample - playhead;
-            // Calculate the index of the first current voice
-            let current = local_sample * max_voices;
-
-            // If the current sample is the first sample in the buffer,
-            // Copy from the last voices
-            if local_sample == 0 && !self.last_voices.is_empty() {
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        self.last_voices.as_ptr(),
-                        self.voice_buffer.as_mut_ptr(),
-                        max_voices,
-                    );
-                }
-            }
-
-            // If the current sample is not the first sample in the buffer,
-            // copy the previous voices to the current index
-            if local_sample > 0 {
-                let previous = (local_sample - 1) * max_voices;
-                unsafe {
-                    std::ptr::copy_nonoverlapping(
-                        self.voice_buffer[previous..].as_ptr(),
-                        self.voice_buffer[current..].as_mut_ptr(),
-                        max_voices,
-                    );
-                }
+        // Render the whole track through the graph if it is frozen
+        if self.freeze_cache.needs_render() {
+            let mut rendered = vec![0.0; rendered_len];
+            let chunk_len = buffer_size * self.audio_ctx.channels;
+            for (index, output) in rendered.chunks_exact_mut(chunk_len).enumerate() {
+                self.render(true, index * buffer_size, output)?;
             }
+            self.freeze_cache.set_rendered(rendered);
 
-            // Increment age for live midi voices
-            for &index in self.live_voices.values() {
-                self.voice_buffer[current + index].age += 1.0 / self.audio_ctx.sample_rate as f32;
-            }
-
-            // Process the sequenced voices when playing
-            if is_playing {
-                // Increment age for sequenced voices
-                for (index, _) in self.active_voices.iter() {
-                    self.voice_buffer[current + index].age +=
-                        1.0 / self.audio_ctx.sample_rate as f32;
-                }
-
-                // Consume the events in this sample
-                while let Some(event) = self.events.get(self.event_cursor) {
-                    // Break if the event is in future
-                    if event.sample_index > sample {
-                        break;
-                    }
-                    // If the event is the past event, skip the event
-                    if event.sample_index < sample {
-                        self.event_cursor += 1;
-                        continue;
-                    }
-
-                    // Copy the frequency and velocity to avoid reference issues
-                    let frequency = event.frequency;
-                    let velocity = event.velocity;
+            // Reset the voices and the node state after rendering
+            self.seek(0);
+            self.graph.prepare()?;
+        }
 
-                    if event.is_note_on {
-                        // Start playing the note from the sample
-                        let voice_index = self.find_or_steal_voice(frequency);
-                        // Set the new voice to the voice buffer
-                        self.voice_buffer[current + voice_index] =
-                            Voice::new(frequency, velocity, 0.0, true);
-                    } else {
-                        // Remove the active voice whose frequency matches the event frequency
-                        if let Some(remove_index) = self
-                            .active_voices
-                            .iter()
-                            .position(|(_, freq)| *freq == event.frequency)
-                        {
-                            // Remove the index from the active_voices and get the voice index
-                            let (voice_index, _) = self.active_voices.remove(remove_index).unwrap();
-                            // Mark the voice index as free
-                            self.free_voices.push(voice_index);
-                            self.voice_buffer[current + voice_index].is_active = false;
-                            self.voice_buffer[current + voice_index].age = 0.0;
-                        }
-                    }
+        Ok(())
+    }
 
-                    // Increment the event cursor
-                    self.event_cursor += 1;
-                }
-            }
+    fn process(
+        &mut self,
+        is_playing: bool,
+        playhead: usize,
+        output: &mut [f32],
+    ) -> Result<(), GraphError> {
+        // Play back the rendered output if the track is frozen
+        // Live MIDI input is not rendered into the frozen output
+        if is_playing
+            && self
+                .freeze_cache
+                .read(playhead * self.audio_ctx.channels, output)
+        {
+            return Ok(());
         }
 
-        // Copy the last voices
-        let last = (self.audio_ctx.buffer_size - 1) * max_voices;
-        self.last_voices
-            .clone_from_slice(&self.voice_buffer[last..last + max_voices]);
-
-        // Get a pointer to the voice buffer
-        let input_ptr = self.voice_buffer.as_ptr() as *const u8;
-        // Process the graph
-        self.graph
-            .process(&[input_ptr], &[output.as_mut_ptr() as *mut u8]);
+        self.render(is_playing, playhead, output)
     }
 
     // --- ANY CASTING ---
@@ -27,6 +27,73 @@ impl NoteRegion {
         }
     }
 
+    // --- TIMING ---
+
+    /// Returns the end beats of the region.
+    pub fn get_end(&self) -> Beats {
+        self.start + self.duration
+    }
+
+    /// Returns whether the region plays at the given beats, which is from the start up to the end exclusive.
+    pub fn is_active_at(&self, at: Beats) -> bool {
+        self.start <= at && at < self.get_end()
+    }
+
+    /// Returns whether the region overlaps the range between the given beats,
+    /// including when the range contains the whole region.
+    pub fn is_active_between(&self, start: Beats, end: Beats) -> bool {
+        start < self.get_end() && end > self.start
+    }
+
+    // --- SPLITTING ---
+
+    /// Splits the region at the given beats, shortening this region and returning the latter part.
+    /// Notes starting after the beats are moved to the latter part, and the notes crossing the beats are cut at the end of this region.
+    /// Returns None if the beats is not inside the region.
+    pub fn split_at(&mut self, at: Beats) -> Option<NoteRegion> {
+        if at <= self.start || at >= self.get_end() {
+            return None;
+        }
+        let split_offset = at - self.start;
+        let mut latter = NoteRegion::new(at, self.get_end() - at);
+
+        // Move the notes after the split beats to the latter region
+        let mut moved: Vec<(NoteID, Note)> = self
+            .notes
+            .iter()
+            .filter(|(_, note)| note.start >= split_offset)
+            .map(|(id, note)| (*id, note.clone()))
+            .collect();
+        moved.sort_by_key(|(id, _)| id.0);
+        for (id, mut note) in moved {
+            self.notes.remove(&id);
+            note.start = note.start - split_offset;
+            latter.add_note(note);
+        }
+
+        self.duration = split_offset;
+        Some(latter)
+    }
+
+    /// Returns whether the other region directly follows this region.
+    pub fn can_merge(&self, other: &NoteRegion) -> bool {
+        // Allow a small error caused by splitting the beats
+        (self.get_end().0 - other.start.0).abs() < 1e-9
+    }
+
+    /// Appends the notes of the following region to this region, extending the duration.
+    pub fn merge(&mut self, other: &NoteRegion) {
+        let offset = other.start - self.start;
+        let mut notes: Vec<(&NoteID, &Note)> = other.notes.iter().collect();
+        notes.sort_by_key(|(id, _)| id.0);
+        for (_, note) in notes {
+            let mut note = note.clone();
+            note.start = note.start + offset;
+            self.add_note(note);
+        }
+        self.duration = other.get_end() - self.start;
+    }
+
     // --- NOTE ID GENERATION ---
 
     /// Sets the next note ID to the given ID.
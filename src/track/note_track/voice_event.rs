@@ -1,4 +1,4 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(super) struct VoiceEvent {
     pub sample_index: usize,
     pub frequency: f32,
@@ -0,0 +1,123 @@
+use crate::{
+    data_types::AudioContext,
+    graph::{Graph, SavedGraph},
+    mixer::ProjectFileError,
+    node::NodeRegistry,
+    track::{
+        CollisionPolicy, RegionID, Track,
+        audio_track::{AudioRegion, AudioTrack},
+        bus_track::BusTrack,
+        note_track::{NoteRegion, NoteTrack},
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A track saved in a project file, tagged with its type.
+/// Frozen tracks are saved without the rendered output, which is rendered again when the project is prepared.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) enum SavedTrack {
+    Audio {
+        graph: SavedGraph,
+        regions: HashMap<RegionID, AudioRegion>,
+        next_region_id: usize,
+        collision_policy: CollisionPolicy,
+        input_gain: f32,
+        is_frozen: bool,
+    },
+    Note {
+        graph: SavedGraph,
+        regions: HashMap<RegionID, NoteRegion>,
+        next_region_id: usize,
+        collision_policy: CollisionPolicy,
+        is_frozen: bool,
+    },
+    Bus {
+        graph: SavedGraph,
+        input_gain: f32,
+    },
+}
+
+impl SavedTrack {
+    /// Saves the track, or returns None if the track is not one of the track types of the engine.
+    pub(crate) fn from_track(track: &dyn Track) -> Option<Self> {
+        let any = track.as_any();
+        if let Some(audio_track) = any.downcast_ref::<AudioTrack>() {
+            Some(SavedTrack::Audio {
+                graph: audio_track.get_graph().to_saved(),
+                regions: audio_track.get_all_regions().clone(),
+                next_region_id: audio_track.get_next_region_id(),
+                collision_policy: audio_track.get_collision_policy(),
+                input_gain: audio_track.get_input_gain(),
+                is_frozen: audio_track.is_frozen(),
+            })
+        } else if let Some(note_track) = any.downcast_ref::<NoteTrack>() {
+            Some(SavedTrack::Note {
+                graph: note_track.get_graph().to_saved(),
+                regions: note_track.get_all_regions().clone(),
+                next_region_id: note_track.get_next_region_id(),
+                collision_policy: note_track.get_collision_policy(),
+                is_frozen: note_track.is_frozen(),
+            })
+        } else {
+            any.downcast_ref::<BusTrack>()
+                .map(|bus_track| SavedTrack::Bus {
+                    graph: bus_track.get_graph().to_saved(),
+                    input_gain: bus_track.get_input_gain(),
+                })
+        }
+    }
+
+    /// Restores the track, creating the nodes of its graph with the registry.
+    /// The track must be prepared before processing.
+    pub(crate) fn into_track(
+        self,
+        audio_ctx: &AudioContext,
+        registry: &NodeRegistry,
+    ) -> Result<Box<dyn Track>, ProjectFileError> {
+        match self {
+            SavedTrack::Audio {
+                graph,
+                regions,
+                next_region_id,
+                collision_policy,
+                input_gain,
+                is_frozen,
+            } => {
+                let mut track = AudioTrack::new(audio_ctx.clone());
+                track.set_graph(Graph::from_saved(graph, audio_ctx, registry)?);
+                track.set_regions(regions);
+                track.set_next_region_id(next_region_id);
+                track.set_collision_policy(collision_policy);
+                track.set_input_gain(input_gain);
+                if is_frozen {
+                    track.freeze();
+                }
+                Ok(Box::new(track))
+            }
+            SavedTrack::Note {
+                graph,
+                regions,
+                next_region_id,
+                collision_policy,
+                is_frozen,
+            } => {
+                let mut track = NoteTrack::new(audio_ctx.clone());
+                track.set_graph(Graph::from_saved(graph, audio_ctx, registry)?);
+                track.set_regions(regions);
+                track.set_next_region_id(next_region_id);
+                track.set_collision_policy(collision_policy);
+                if is_frozen {
+                    track.freeze();
+                }
+                Ok(Box::new(track))
+            }
+            SavedTrack::Bus { graph, input_gain } => {
+                let mut track = BusTrack::new(audio_ctx.clone());
+                track.set_graph(Graph::from_saved(graph, audio_ctx, registry)?);
+                track.set_input_gain(input_gain);
+                Ok(Box::new(track))
+            }
+        }
+    }
+}